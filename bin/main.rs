@@ -1,11 +1,85 @@
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use indicatif::{ProgressBar, ProgressStyle};
 use pixel_sort::*;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 fn main() {
-    let matches = App::new("pixel-sort")
+    let matches = app().get_matches();
+    let opts = parse_opts(&matches);
+
+    let input = matches.value_of("input").unwrap();
+
+    if matches.subcommand_matches("analyze").is_some() {
+        analyze(input, &opts, matches.value_of("fn").unwrap_or("max"));
+        return;
+    }
+
+    // the output is only optional for `analyze`, which clap can't express for a subcommand
+    let output = matches.value_of("output").unwrap_or_else(|| {
+        clap::Error::with_description(
+            &format!(
+                "The following required arguments were not provided:\n    <output>\n\n{}",
+                matches.usage()
+            ),
+            clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit()
+    });
+
+    let jobs = matches
+        .value_of("jobs")
+        .and_then(|j| j.parse().ok())
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+
+    let force = matches.occurrences_of("force") >= 1;
+
+    if matches.occurrences_of("sequence") >= 1 {
+        let output = find_parts(output);
+        let frames = FileSeq::new(input)
+            .enumerate()
+            .map(|(i, input)| {
+                let output = format!("{}{:0>width$}{}", output.0, i, output.1, width = output.2);
+
+                (PathBuf::from(input), PathBuf::from(output))
+            })
+            .collect();
+
+        sort_batch(frames, &opts, jobs, force);
+    } else if Path::new(input).is_dir() {
+        let mut frames = Vec::new();
+
+        walk_dir(
+            Path::new(input),
+            Path::new(input),
+            Path::new(output),
+            &mut frames,
+        );
+        frames.sort();
+        sort_batch(frames, &opts, jobs, force);
+    } else if input.contains(['*', '?', '[']) {
+        sort_batch(glob_files(input, output), &opts, jobs, force);
+    } else {
+        pixel_sort::img::process_image(input, output, opts);
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("pixel-sort")
         .arg(Arg::with_name("sequence").long("seq"))
+        .arg(
+            Arg::with_name("jobs")
+                .long("jobs")
+                .short("j")
+                .takes_value(true),
+        )
+        .arg(Arg::with_name("force").long("force"))
         .arg(Arg::with_name("input").takes_value(true).required(true))
-        .arg(Arg::with_name("output").takes_value(true).required(true))
+        .arg(Arg::with_name("output").takes_value(true))
         .arg(Arg::with_name("resize").takes_value(true).long("resize"))
         .arg(
             Arg::with_name("internal-scale")
@@ -24,95 +98,629 @@ fn main() {
                 .takes_value(true)
                 .default_value("255"),
         )
+        .arg(
+            Arg::with_name("min-span")
+                .long("min-span")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-span")
+                .long("max-span")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("span-chance")
+                .long("span-chance")
+                .takes_value(true),
+        )
         .arg(Arg::with_name("angle").long("angle").takes_value(true))
         .arg(Arg::with_name("vertical").long("vertical"))
         .arg(Arg::with_name("split").long("split"))
+        .arg(Arg::with_name("labels").long("labels").takes_value(true))
+        .arg(Arg::with_name("kmeans").long("kmeans").takes_value(true))
+        .arg(Arg::with_name("slic").long("slic").takes_value(true))
+        // only one way of finding regions at a time
+        .group(ArgGroup::with_name("segments").args(&["labels", "kmeans", "slic"]))
         .arg(
-            Arg::with_name("fn")
-                .long("fn")
+            Arg::with_name("compactness")
+                .long("compactness")
+                .takes_value(true)
+                .requires("slic"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .requires("kmeans"),
+        )
+        .arg(
+            Arg::with_name("region")
+                .long("region")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true)
+                .requires("segments"),
+        )
+        .arg(
+            Arg::with_name("regions")
+                .long("regions")
+                .takes_value(true)
+                .requires("segments"),
+        )
+        .arg(
+            Arg::with_name("channels")
+                .long("channels")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("block")
+                .long("block")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "labels",
+                    "kmeans",
+                    "slic",
+                    "region",
+                    "regions",
+                    "channels",
+                    "component",
+                ]),
+        )
+        .arg(
+            Arg::with_name("debug-intervals")
+                .long("debug-intervals")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("debug-direction")
+                .long("debug-direction")
+                .requires("debug-intervals"),
+        )
+        .arg(
+            Arg::with_name("block-key")
+                .long("block-key")
+                .takes_value(true)
+                .possible_values(&["mean", "median"])
+                .requires("block"),
+        )
+        .arg(
+            Arg::with_name("component")
+                .long("component")
+                .takes_value(true)
+                .possible_values(&["luma", "hue", "chroma", "alpha"])
+                .conflicts_with("channels"),
+        )
+        .arg(Arg::with_name("opacity").long("opacity").takes_value(true))
+        .arg(
+            Arg::with_name("blend")
+                .long("blend")
                 .takes_value(true)
                 .possible_values(&[
-                    "red",
-                    "green",
-                    "blue",
-                    "max",
-                    "min",
-                    "chroma",
-                    "hue",
-                    "luma",
-                    "saturation",
-                    "brightness",
+                    "normal",
+                    "lighten",
+                    "darken",
+                    "difference",
+                    "screen",
+                    "multiply",
+                    "overlay",
                 ]),
         )
+        .arg(
+            Arg::with_name("blend-mask")
+                .long("blend-mask")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("feather")
+                .long("feather")
+                .takes_value(true)
+                .requires("blend-mask"),
+        )
+        .arg(
+            Arg::with_name("fn")
+                .long("fn")
+                .takes_value(true)
+                .possible_values(&SORT_FNS),
+        )
         .arg(
             Arg::with_name("interval")
                 .long("interval")
                 .short("i")
                 .takes_value(true)
-                .possible_values(&["random", "threshold"]),
+                .possible_values(&["random", "threshold", "hysteresis", "color", "noise"]),
+        )
+        .arg(Arg::with_name("hue").long("hue").takes_value(true))
+        .arg(
+            Arg::with_name("saturation")
+                .long("saturation")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("lightness")
+                .long("lightness")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("until-fn")
+                .long("until-fn")
+                .takes_value(true)
+                .possible_values(&SORT_FNS),
+        )
+        .arg(
+            Arg::with_name("until-min")
+                .long("until-min")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("until-max")
+                .long("until-max")
+                .takes_value(true),
+        )
+        .arg(Arg::with_name("hold").long("hold").takes_value(true))
+        .arg(
+            Arg::with_name("interval-map")
+                .long("interval-map")
+                .takes_value(true)
+                .conflicts_with("interval"),
+        )
+        .arg(
+            Arg::with_name("map-threshold")
+                .long("map-threshold")
+                .takes_value(true)
+                .requires("interval-map"),
+        )
+        .arg(
+            Arg::with_name("map-delta")
+                .long("map-delta")
+                .takes_value(true)
+                .requires("interval-map"),
+        )
+        .arg(
+            Arg::with_name("noise-scale")
+                .long("noise-scale")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("noise-seed")
+                .long("noise-seed")
+                .takes_value(true),
         )
         .arg(Arg::with_name("invert").long("invert"))
         .arg(Arg::with_name("reverse").long("reverse"))
+        .subcommand(SubCommand::with_name("analyze"))
         .subcommand(SubCommand::with_name("linear"))
+        .subcommand(SubCommand::with_name("diagonal").arg(Arg::with_name("anti").long("anti")))
+        .subcommand(SubCommand::with_name("zigzag"))
         .subcommand(SubCommand::with_name("spiral"))
+        .subcommand(
+            SubCommand::with_name("polygon")
+                .arg(Arg::with_name("cx").takes_value(true))
+                .arg(Arg::with_name("cy").takes_value(true))
+                .arg(Arg::with_name("sides").long("sides").takes_value(true))
+                .arg(
+                    Arg::with_name("rotation")
+                        .long("rotation")
+                        .takes_value(true),
+                )
+                .arg(Arg::with_name("star").long("star").takes_value(true))
+                .arg(Arg::with_name("fit").long("fit").conflicts_with("star")),
+        )
         .subcommand(
             SubCommand::with_name("circle")
-                .arg(Arg::with_name("cx").takes_value(true).required(true))
-                .arg(Arg::with_name("cy").takes_value(true).required(true)),
+                .arg(Arg::with_name("cx").takes_value(true))
+                .arg(Arg::with_name("cy").takes_value(true))
+                .arg(
+                    Arg::with_name("centre")
+                        .long("centre")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(Arg::with_name("ratio").long("ratio").takes_value(true))
+                .arg(
+                    Arg::with_name("rotation")
+                        .long("rotation")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("swirl")
+                .arg(Arg::with_name("cx").takes_value(true))
+                .arg(Arg::with_name("cy").takes_value(true))
+                .arg(Arg::with_name("spacing").long("spacing").takes_value(true))
+                .arg(
+                    Arg::with_name("growth")
+                        .long("growth")
+                        .takes_value(true)
+                        .conflicts_with("spacing"),
+                )
+                .arg(Arg::with_name("arms").long("arms").takes_value(true))
+                .arg(Arg::with_name("ccw").long("ccw")),
+        )
+        .subcommand(
+            SubCommand::with_name("radial")
+                .arg(Arg::with_name("cx").takes_value(true))
+                .arg(Arg::with_name("cy").takes_value(true))
+                .arg(Arg::with_name("rays").long("rays").takes_value(true))
+                .arg(Arg::with_name("inward").long("inward")),
+        )
+        .subcommand(
+            SubCommand::with_name("flow")
+                .arg(Arg::with_name("scale").long("scale").takes_value(true))
+                .arg(Arg::with_name("octaves").long("octaves").takes_value(true))
+                .arg(Arg::with_name("seed").long("seed").takes_value(true))
+                .arg(Arg::with_name("length").long("length").takes_value(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("contour")
+                .arg(Arg::with_name("gradient").long("gradient"))
+                .arg(Arg::with_name("smooth").long("smooth").takes_value(true))
+                .arg(Arg::with_name("length").long("length").takes_value(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("custom")
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("d")
+                        .long("d")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true),
+                )
+                .arg(Arg::with_name("space").long("space").takes_value(true)),
         )
         .subcommand(
             SubCommand::with_name("sine")
                 .arg(Arg::with_name("amp").takes_value(true).required(true))
                 .arg(Arg::with_name("lam").takes_value(true).required(true))
-                .arg(Arg::with_name("offset").takes_value(true).required(true)),
+                .arg(Arg::with_name("offset").takes_value(true).required(true))
+                .arg(
+                    Arg::with_name("shape")
+                        .long("shape")
+                        .takes_value(true)
+                        .possible_values(&WAVEFORMS),
+                )
+                .arg(
+                    Arg::with_name("harmonic")
+                        .long("harmonic")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::with_name("growth")
+                        .long("growth")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
+                ),
         )
-        .get_matches();
+}
+
+fn parse_opts(matches: &ArgMatches) -> Opts {
+    // diagonals and custom paths have no angle for `--vertical` to turn
+    if matches.occurrences_of("vertical") >= 1
+        && (matches.subcommand_matches("diagonal").is_some()
+            || matches.subcommand_matches("custom").is_some())
+    {
+        panic!("--vertical doesn't apply to diagonal or custom paths");
+    }
+
+    let sort_fn = matches
+        .value_of("fn")
+        .map(|f| parse_sort_fn(f).expect("invalid sort function"))
+        .unwrap_or(pixel_max);
 
-    let opts = Opts {
-        sort_type: if let Some(_) = matches.subcommand_matches("linear") {
+    let (min, auto_min) = parse_bound(matches.value_of("min").unwrap()).expect("invalid min");
+    let (max, auto_max) = parse_bound(matches.value_of("max").unwrap()).expect("invalid max");
+    // random and noise spans read `--min` and `--max` as lengths, not keys
+    if (auto_min.is_some() || auto_max.is_some())
+        && matches!(
+            matches.value_of("interval").unwrap_or("random"),
+            "random" | "noise"
+        )
+    {
+        panic!("`auto` and percentile bounds don't work with random or noise intervals");
+    }
+
+    Opts {
+        sort_type: if matches.subcommand_matches("linear").is_some() {
             SortType::Linear
-        } else if let Some(_) = matches.subcommand_matches("spiral") {
+        } else if let Some(matches) = matches.subcommand_matches("diagonal") {
+            SortType::Diagonal {
+                anti: matches.is_present("anti"),
+            }
+        } else if matches.subcommand_matches("zigzag").is_some() {
+            SortType::Zigzag
+        } else if matches.subcommand_matches("spiral").is_some() {
             SortType::Spiral
+        } else if let Some(matches) = matches.subcommand_matches("polygon") {
+            SortType::Polygon {
+                cx: parse_centre(matches, "cx"),
+                cy: parse_centre(matches, "cy"),
+                sides: matches
+                    .value_of("sides")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(6),
+                rotation: matches
+                    .value_of("rotation")
+                    .map(|r| r.parse().unwrap())
+                    .unwrap_or(0.0),
+                star: matches.value_of("star").map(|s| s.parse().unwrap()),
+                fit: matches.is_present("fit"),
+            }
         } else if let Some(matches) = matches.subcommand_matches("circle") {
-            SortType::Circle {
-                cx: matches.value_of("cx").unwrap().parse().unwrap(),
-                cy: matches.value_of("cy").unwrap().parse().unwrap(),
+            if matches.is_present("cx") && !matches.is_present("cy") {
+                panic!("missing cy");
+            }
+
+            let mut centres = matches
+                .value_of("cx")
+                .into_iter()
+                .zip(matches.value_of("cy"))
+                .chain(
+                    matches
+                        .values_of("centre")
+                        .into_iter()
+                        .flatten()
+                        .map(|c| c.split_once(',').expect("invalid centre")),
+                )
+                .map(|(cx, cy)| {
+                    (
+                        parse_coord(cx).expect("invalid centre"),
+                        parse_coord(cy).expect("invalid centre"),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            if centres.is_empty() {
+                centres.push((Coord::Percent(50.0), Coord::Percent(50.0)));
             }
+
+            let ratio = matches.value_of("ratio").map(|r| r.parse().unwrap());
+            let rotation = matches.value_of("rotation").map(|r| r.parse().unwrap());
+
+            if centres.len() > 1 && (ratio.is_some() || rotation.is_some()) {
+                panic!("--ratio and --rotation only work with a single centre");
+            }
+
+            if centres.len() > 1 {
+                SortType::MultiCircle { centres }
+            } else if ratio.is_some() || rotation.is_some() {
+                SortType::Ellipse {
+                    cx: centres[0].0,
+                    cy: centres[0].1,
+                    ratio: ratio.unwrap_or(1.0),
+                    rotation: rotation.unwrap_or(0.0),
+                }
+            } else {
+                SortType::Circle {
+                    cx: centres[0].0,
+                    cy: centres[0].1,
+                }
+            }
+        } else if let Some(matches) = matches.subcommand_matches("swirl") {
+            SortType::Swirl {
+                cx: parse_centre(matches, "cx"),
+                cy: parse_centre(matches, "cy"),
+                shape: match matches.value_of("growth") {
+                    Some(growth) => SpiralShape::Logarithmic {
+                        growth: growth.parse().unwrap(),
+                    },
+                    None => SpiralShape::Archimedean {
+                        spacing: matches
+                            .value_of("spacing")
+                            .map(|s| s.parse().unwrap())
+                            .unwrap_or(4.0),
+                    },
+                },
+                arms: matches
+                    .value_of("arms")
+                    .map(|a| a.parse().unwrap())
+                    .unwrap_or(1),
+                clockwise: !matches.is_present("ccw"),
+            }
+        } else if let Some(matches) = matches.subcommand_matches("radial") {
+            SortType::Radial {
+                cx: parse_centre(matches, "cx"),
+                cy: parse_centre(matches, "cy"),
+                rays: matches
+                    .value_of("rays")
+                    .map(|r| r.parse().unwrap())
+                    .unwrap_or(0),
+                inward: matches.is_present("inward"),
+            }
+        } else if let Some(matches) = matches.subcommand_matches("flow") {
+            SortType::FlowField {
+                scale: matches
+                    .value_of("scale")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(200.0),
+                octaves: matches
+                    .value_of("octaves")
+                    .map(|o| o.parse().unwrap())
+                    .unwrap_or(2),
+                seed: matches
+                    .value_of("seed")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(0),
+                length: matches
+                    .value_of("length")
+                    .map(|l| l.parse().unwrap())
+                    .unwrap_or(0),
+            }
+        } else if let Some(matches) = matches.subcommand_matches("contour") {
+            SortType::Contour {
+                along: if matches.is_present("gradient") {
+                    Contour::Gradient
+                } else {
+                    Contour::Isophote
+                },
+                smooth: matches
+                    .value_of("smooth")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(2.0),
+                length: matches
+                    .value_of("length")
+                    .map(|l| l.parse().unwrap())
+                    .unwrap_or(0),
+            }
+        } else if let Some(matches) = matches.subcommand_matches("custom") {
+            let mut paths = Vec::new();
+            let mut space = None;
+
+            for file in matches.values_of("file").into_iter().flatten() {
+                let (p, s) = pixel_sort::custom::load(file)
+                    .unwrap_or_else(|e| panic!("could not load `{}`: {}", file, e));
+
+                paths.extend(p);
+                space = space.or(s);
+            }
+
+            for d in matches.values_of("d").into_iter().flatten() {
+                paths.extend(pixel_sort::custom::parse_path_data(d).unwrap());
+            }
+
+            if let Some(s) = matches.value_of("space") {
+                let (w, h) = s.split_once(':').expect("invalid space");
+
+                space = Some(
+                    pixel_sort::custom::space(0.0, 0.0, w.parse().unwrap(), h.parse().unwrap())
+                        .expect("invalid space"),
+                );
+            }
+
+            SortType::Custom { paths, space }
         } else if let Some(matches) = matches.subcommand_matches("sine") {
-            SortType::Sine {
-                amp: matches.value_of("amp").unwrap().parse().unwrap(),
-                lam: matches.value_of("lam").unwrap().parse().unwrap(),
-                offset: matches.value_of("offset").unwrap().parse().unwrap(),
+            let amp = matches.value_of("amp").unwrap().parse().unwrap();
+            let lam = matches.value_of("lam").unwrap().parse().unwrap();
+            let offset = matches.value_of("offset").unwrap().parse().unwrap();
+
+            if matches.is_present("shape")
+                || matches.is_present("harmonic")
+                || matches.is_present("growth")
+            {
+                let base = Harmonic {
+                    shape: matches
+                        .value_of("shape")
+                        .map(|s| parse_waveform(s).unwrap())
+                        .unwrap_or(Waveform::Sine),
+                    amp,
+                    lam,
+                    offset,
+                };
+
+                let harmonics = std::iter::once(base)
+                    .chain(
+                        matches
+                            .values_of("harmonic")
+                            .into_iter()
+                            .flatten()
+                            .map(|h| parse_harmonic(h).expect("invalid harmonic")),
+                    )
+                    .collect();
+
+                SortType::Wave {
+                    harmonics,
+                    growth: matches
+                        .value_of("growth")
+                        .map(|g| g.parse().unwrap())
+                        .unwrap_or(0.0),
+                }
+            } else {
+                SortType::Sine { amp, lam, offset }
             }
         } else {
             SortType::Linear
         },
-        sort_fn: match matches.value_of("fn") {
-            Some("red") => pixel_red,
-            Some("green") => pixel_green,
-            Some("blue") => pixel_blue,
-            Some("max") => pixel_max,
-            Some("min") => pixel_min,
-            Some("chroma") => pixel_chroma,
-            Some("hue") => pixel_hue,
-            Some("saturation") => pixel_saturation,
-            Some("brightness") => pixel_brightness,
-            Some("luma") => pixel_luma,
-            None => pixel_max,
-            _ => panic!("invalid sort function"),
-        },
-        interval: match matches.value_of("interval") {
-            Some("random") => IntervalType::Random,
-            Some("threshold") => IntervalType::Threshold,
-            None => IntervalType::Random,
-            _ => panic!("invalid interval type"),
+        sort_fn,
+        interval: match matches.value_of("interval-map") {
+            Some(map) => IntervalType::Map {
+                map: image::open(map).unwrap().to_luma8(),
+                threshold: match (
+                    matches.value_of("map-threshold"),
+                    matches.is_present("map-delta"),
+                ) {
+                    (Some(t), _) => Some(t.parse().unwrap()),
+                    (None, true) => None,
+                    (None, false) => Some(128),
+                },
+                delta: matches.value_of("map-delta").map(|d| d.parse().unwrap()),
+            },
+            None => match matches.value_of("interval") {
+                Some("random") => IntervalType::Random,
+                Some("threshold") => IntervalType::Threshold,
+                Some("hysteresis") => IntervalType::Hysteresis {
+                    key: matches
+                        .value_of("until-fn")
+                        .map(|f| parse_sort_fn(f).expect("invalid sort function"))
+                        .unwrap_or(sort_fn),
+                    min: matches
+                        .value_of("until-min")
+                        .map(|m| m.parse().expect("invalid until-min")),
+                    max: matches
+                        .value_of("until-max")
+                        .map(|m| m.parse().expect("invalid until-max")),
+                    hold: matches
+                        .value_of("hold")
+                        .map(|h| h.parse().unwrap())
+                        .unwrap_or(0),
+                },
+                Some("color") => {
+                    let range = |name| {
+                        matches
+                            .value_of(name)
+                            .map(|r| parse_range(r).expect("invalid range"))
+                    };
+
+                    IntervalType::Color(ColorRange {
+                        hue: range("hue"),
+                        saturation: range("saturation").unwrap_or((0.0, 100.0)),
+                        lightness: range("lightness").unwrap_or((0.0, 100.0)),
+                    })
+                }
+                Some("noise") => IntervalType::Noise {
+                    scale: matches
+                        .value_of("noise-scale")
+                        .map(|s| s.parse().unwrap())
+                        .unwrap_or(100.0),
+                    seed: matches
+                        .value_of("noise-seed")
+                        .map(|s| s.parse().unwrap())
+                        .unwrap_or(0),
+                },
+                None => IntervalType::Random,
+                _ => panic!("invalid interval type"),
+            },
         },
         mask_alpha: false,
         invert: matches.occurrences_of("invert") >= 1,
         reverse: matches.occurrences_of("reverse") >= 1,
         split: matches.occurrences_of("split") >= 1,
-        min: matches.value_of("min").unwrap().parse().unwrap(),
-        max: matches.value_of("max").unwrap().parse().unwrap(),
+        min,
+        max,
+        auto_min,
+        auto_max,
+        min_span: matches
+            .value_of("min-span")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or(0),
+        max_span: matches
+            .value_of("max-span")
+            .map(|s| s.parse().unwrap())
+            .unwrap_or(usize::MAX),
+        span_chance: matches
+            .value_of("span-chance")
+            .map(|c| f64::from(parse_fraction(c).expect("invalid span chance")))
+            .unwrap_or(1.0),
         angle: matches
             .value_of("angle")
             .and_then(|a| a.parse().ok())
@@ -120,41 +728,304 @@ fn main() {
         vertical: matches.occurrences_of("vertical") >= 1,
         resize: matches.value_of("resize").and_then(parse_scale),
         internal_scale: matches.value_of("internal-scale").and_then(parse_scale),
-    };
+        segments: if let Some(labels) = matches.value_of("labels") {
+            Some(Segments::Labels(image::open(labels).unwrap().to_rgba8()))
+        } else if let Some(k) = matches.value_of("kmeans") {
+            Some(Segments::KMeans {
+                k: k.parse().unwrap(),
+                seed: matches
+                    .value_of("seed")
+                    .map(|s| s.parse().unwrap())
+                    .unwrap_or(0),
+            })
+        } else {
+            matches.value_of("slic").map(|size| Segments::Slic {
+                size: size.parse().unwrap(),
+                compactness: matches
+                    .value_of("compactness")
+                    .map(|c| c.parse().unwrap())
+                    .unwrap_or(20.0),
+            })
+        },
+        regions: matches
+            .values_of("region")
+            .into_iter()
+            .flatten()
+            .map(String::from)
+            .chain(matches.value_of("regions").into_iter().flat_map(|file| {
+                std::fs::read_to_string(file)
+                    .unwrap()
+                    .lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            }))
+            .map(|region| parse_region(&region).expect("invalid region"))
+            .collect(),
+        channels: matches
+            .value_of("channels")
+            .map(|c| parse_channels(c).expect("invalid channels"))
+            .unwrap_or_default(),
+        component: match matches.value_of("component") {
+            Some("luma") => Some(pixel_sort::Component::Lightness),
+            Some("hue") => Some(pixel_sort::Component::Hue),
+            Some("chroma") => Some(pixel_sort::Component::Chroma),
+            Some("alpha") => Some(pixel_sort::Component::Alpha),
+            None => None,
+            _ => panic!("invalid component"),
+        },
+        debug_intervals: matches.value_of("debug-intervals").map(PathBuf::from),
+        debug_direction: matches.occurrences_of("debug-direction") >= 1,
+        block: matches.value_of("block").map(|b| {
+            let (width, height) = parse_block(b).expect("invalid block size");
 
-    let input = matches.value_of("input").unwrap();
-    let output = matches.value_of("output").unwrap();
+            Block {
+                width,
+                height,
+                key: match matches.value_of("block-key") {
+                    Some("mean") | None => BlockKey::Mean,
+                    Some("median") => BlockKey::Median,
+                    _ => panic!("invalid block key"),
+                },
+            }
+        }),
+        blend: if matches.is_present("opacity")
+            || matches.is_present("blend")
+            || matches.is_present("blend-mask")
+        {
+            Some(Blend {
+                mode: match matches.value_of("blend") {
+                    Some("normal") | None => BlendMode::Normal,
+                    Some("lighten") => BlendMode::Lighten,
+                    Some("darken") => BlendMode::Darken,
+                    Some("difference") => BlendMode::Difference,
+                    Some("screen") => BlendMode::Screen,
+                    Some("multiply") => BlendMode::Multiply,
+                    Some("overlay") => BlendMode::Overlay,
+                    _ => panic!("invalid blend mode"),
+                },
+                opacity: matches
+                    .value_of("opacity")
+                    .map(|o| parse_fraction(o).expect("invalid opacity"))
+                    .unwrap_or(1.0),
+                mask: matches
+                    .value_of("blend-mask")
+                    .map(|mask| image::open(mask).unwrap().to_luma8()),
+                feather: matches
+                    .value_of("feather")
+                    .map(|f| f.parse().unwrap())
+                    .unwrap_or(0.0),
+            })
+        } else {
+            None
+        },
+    }
+}
 
-    if matches.occurrences_of("sequence") >= 1 {
-        let output = find_parts(output);
-        let pb = indicatif::ProgressBar::new(0).with_style(
-            indicatif::ProgressStyle::default_bar()
-                .template("{prefix} [{bar:40.cyan/blue}] {pos::>5}/{len}")
-                .progress_chars("=> "),
+fn analyze(input: &str, opts: &Opts, name: &str) {
+    let image = image::open(input).unwrap().to_rgba8();
+    let hist = analyze::histogram(&image, opts.sort_fn);
+    let peak = hist
+        .chunks(16)
+        .map(|c| c.iter().sum::<u64>())
+        .max()
+        .unwrap_or(0);
+
+    println!("Histogram of `{}` over {}", name, input);
+
+    for (i, bin) in hist.chunks(16).enumerate() {
+        let count = bin.iter().sum::<u64>();
+        let bar = (count * 40).checked_div(peak).unwrap_or(0) as usize;
+
+        println!(
+            "{:>3}-{:>3} | {:<40} {}",
+            i * 16,
+            i * 16 + 15,
+            "#".repeat(bar),
+            count
         );
+    }
+
+    let otsu = analyze::otsu(&hist);
+    let percentiles = [10, 20, 50, 80, 90]
+        .iter()
+        .map(|&p| format!("p{} {}", p, analyze::percentile(&hist, p as f32)))
+        .collect::<Vec<_>>();
 
-        for (i, input) in FileSeq::new(input).enumerate() {
-            pb.set_prefix(format!("Sorting `{}`", input));
-            pb.set_position(0);
+    println!();
+    println!("Otsu threshold: {}", otsu);
+    println!("Percentiles:    {}", percentiles.join(", "));
+    println!();
+    println!("Suggested thresholds:");
+    println!(
+        "  --min {}          sort the pixels above the otsu threshold",
+        otsu
+    );
+    println!(
+        "  --max {}          sort the pixels below the otsu threshold",
+        otsu.saturating_sub(1)
+    );
+    println!(
+        "  --min {} --max {} sort the middle 60% of the pixels",
+        analyze::percentile(&hist, 20.0),
+        analyze::percentile(&hist, 80.0)
+    );
+}
 
-            let image = image::open(&input).unwrap();
-            let sorted = pixel_sort::img::sort_image(&pb, image, &opts);
-            let output = format!("{}{:0>width$}{}", output.0, i, output.1, width = output.2);
+fn sort_batch(frames: Vec<(PathBuf, PathBuf)>, opts: &Opts, jobs: usize, force: bool) {
+    if opts.debug_intervals.is_some() {
+        panic!("--debug-intervals only works when sorting a single image");
+    }
 
-            sorted.save(output).unwrap();
+    let total = frames.len();
+
+    // each frame is seeded with its place in the whole batch, so a resumed or parallel
+    // render gives the same frames as a full serial one
+    let frames = frames
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (input, output))| force || !is_up_to_date(input, output))
+        .collect::<Vec<_>>();
+
+    let pb = ProgressBar::new(frames.len() as u64).with_style(
+        ProgressStyle::default_bar()
+            .template("{prefix} [{bar:40.cyan/blue}] {pos:>5}/{len} ({eta}) {wide_msg}")
+            .progress_chars("=> "),
+    );
+
+    pb.set_prefix("Sorting");
+
+    if frames.len() < total {
+        pb.println(format!(
+            "Skipping {} up-to-date frames",
+            total - frames.len()
+        ));
+    }
+
+    let next = AtomicUsize::new(0);
+
+    std::thread::scope(|s| {
+        for _ in 0..jobs.max(1) {
+            s.spawn(|| {
+                while let Some((i, (input, output))) =
+                    frames.get(next.fetch_add(1, Ordering::Relaxed))
+                {
+                    pb.set_message(input.display().to_string());
+
+                    if let Err(e) = sort_frame(input, output, opts, *i as u64) {
+                        pb.println(format!("Failed to sort `{}`: {}", input.display(), e));
+                    }
+
+                    pb.inc(1);
+                }
+            });
+        }
+    });
+
+    pb.finish_with_message("done");
+}
+
+fn sort_frame(
+    input: &Path,
+    output: &Path,
+    opts: &Opts,
+    seed: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let image = image::open(input)?;
+    let sorted = pixel_sort::img::sort_scaled(image, opts, seed);
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // save next to the output and move it into place, so a killed render never leaves
+    // a truncated frame that looks up to date
+    let partial = partial_path(output);
+
+    if let Err(e) = sorted.save(&partial) {
+        let _ = std::fs::remove_file(&partial);
+
+        return Err(e.into());
+    }
+
+    std::fs::rename(&partial, output)?;
+
+    Ok(())
+}
+
+// `dir/frame.png` becomes `dir/.frame.partial.png`, keeping the extension the format is picked by
+fn partial_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+
+    match output.extension() {
+        Some(ext) => output.with_file_name(format!(".{}.partial.{}", stem, ext.to_string_lossy())),
+        None => output.with_file_name(format!(".{}.partial", stem)),
+    }
+}
+
+fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+
+    match (modified(input), modified(output)) {
+        (Some(input), Some(output)) => output >= input,
+        _ => false,
+    }
+}
+
+fn walk_dir(dir: &Path, base: &Path, output: &Path, frames: &mut Vec<(PathBuf, PathBuf)>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        if path.is_dir() {
+            walk_dir(&path, base, output, frames);
+        } else if image::ImageFormat::from_path(&path).is_ok() {
+            let rel = path.strip_prefix(base).unwrap().to_path_buf();
+
+            frames.push((path, output.join(rel)));
         }
-    } else {
-        pixel_sort::img::process_image(input, output, opts);
     }
 }
 
+fn glob_files(pattern: &str, output: &str) -> Vec<(PathBuf, PathBuf)> {
+    let base = Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .filter(|c| *c != Component::CurDir)
+        .collect::<PathBuf>();
+
+    let mut frames = glob::glob(pattern)
+        .expect("invalid glob pattern")
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file() && image::ImageFormat::from_path(path).is_ok())
+        .map(|path| {
+            let plain = path
+                .components()
+                .filter(|c| *c != Component::CurDir)
+                .collect::<PathBuf>();
+
+            let rel = plain.strip_prefix(&base).unwrap_or(&plain);
+            let output = Path::new(output).join(rel);
+
+            (path, output)
+        })
+        .collect::<Vec<_>>();
+
+    frames.sort();
+    frames
+}
+
 fn parse_scale(s: &str) -> Option<Scale> {
-    if s.starts_with('x') {
-        let m = s[1..].parse().ok()?;
+    if let Some(m) = s.strip_prefix('x') {
+        let m = m.parse().ok()?;
 
         Some(Scale::Multiply(m))
-    } else if s.ends_with('%') {
-        let m: f32 = s[..s.len()].parse().ok()?;
+    } else if let Some(m) = s.strip_suffix('%') {
+        let m: f32 = m.parse().ok()?;
 
         Some(Scale::Multiply(m / 100.0))
     } else {
@@ -166,6 +1037,183 @@ fn parse_scale(s: &str) -> Option<Scale> {
     }
 }
 
+// options that act on the whole image, so they mean nothing for a single region
+const IMAGE_ONLY: [&str; 17] = [
+    "jobs",
+    "force",
+    "resize",
+    "internal-scale",
+    "labels",
+    "kmeans",
+    "slic",
+    "region",
+    "regions",
+    "opacity",
+    "blend",
+    "blend-mask",
+    "feather",
+    "block",
+    "block-key",
+    "debug-intervals",
+    "debug-direction",
+];
+
+// `<label>=<options>`, e.g. `#ff0000=--fn hue --vertical linear`
+fn parse_region(s: &str) -> Option<Region> {
+    let (label, args) = s.split_once('=')?;
+    let label = parse_label(label.trim())?;
+
+    // the input and output are required, but regions only use the options
+    let args = ["pixel-sort", "-", "-"]
+        .iter()
+        .copied()
+        .chain(args.split_whitespace());
+
+    let matches = app().get_matches_from(args);
+
+    if let Some(name) = IMAGE_ONLY.iter().find(|&&name| matches.is_present(name)) {
+        panic!("--{} can't be used in a region", name);
+    }
+
+    Some(Region {
+        label,
+        opts: parse_opts(&matches),
+    })
+}
+
+// a colour in the label image (`#rrggbb` or `#rrggbbaa`) or the index of a k-means or slic region
+fn parse_label(s: &str) -> Option<u32> {
+    match s.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => Some(u32::from_str_radix(hex, 16).ok()? << 8 | 0xff),
+        Some(hex) if hex.len() == 8 => u32::from_str_radix(hex, 16).ok(),
+        Some(_) => None,
+        None => s.parse().ok(),
+    }
+}
+
+// a number, `auto` for otsu's method, or a percentile like `p20`
+fn parse_bound(s: &str) -> Option<(usize, Option<Threshold>)> {
+    match s {
+        "auto" => Some((0, Some(Threshold::Otsu))),
+        _ => match s.strip_prefix('p') {
+            Some(p) => Some((0, Some(Threshold::Percentile(p.parse().ok()?)))),
+            None => Some((s.parse().ok()?, None)),
+        },
+    }
+}
+
+// `330-30`
+fn parse_range(s: &str) -> Option<(f32, f32)> {
+    let (start, end) = s.split_once('-')?;
+
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+// `16` or `16x8`
+fn parse_block(s: &str) -> Option<(u32, u32)> {
+    match s.split_once('x') {
+        Some((w, h)) => Some((w.parse().ok()?, h.parse().ok()?)),
+        None => s.parse().ok().map(|n| (n, n)),
+    }
+}
+
+// `rgb`, `rb`, ...
+fn parse_channels(s: &str) -> Option<Vec<usize>> {
+    s.chars()
+        .map(|c| match c {
+            'r' => Some(0),
+            'g' => Some(1),
+            'b' => Some(2),
+            'a' => Some(3),
+            _ => None,
+        })
+        .collect()
+}
+
+// `0.5` or `50%`
+fn parse_fraction(s: &str) -> Option<f32> {
+    match s.strip_suffix('%') {
+        Some(p) => p.parse::<f32>().ok().map(|p| p / 100.0),
+        None => s.parse().ok(),
+    }
+}
+
+const SORT_FNS: [&str; 10] = [
+    "red",
+    "green",
+    "blue",
+    "max",
+    "min",
+    "chroma",
+    "hue",
+    "luma",
+    "saturation",
+    "brightness",
+];
+
+fn parse_sort_fn(s: &str) -> Option<fn(&[u8]) -> u8> {
+    match s {
+        "red" => Some(pixel_red),
+        "green" => Some(pixel_green),
+        "blue" => Some(pixel_blue),
+        "max" => Some(pixel_max),
+        "min" => Some(pixel_min),
+        "chroma" => Some(pixel_chroma),
+        "hue" => Some(pixel_hue),
+        "saturation" => Some(pixel_saturation),
+        "brightness" => Some(pixel_brightness),
+        "luma" => Some(pixel_luma),
+        _ => None,
+    }
+}
+
+const WAVEFORMS: [&str; 5] = ["sine", "square", "triangle", "sawtooth", "noise"];
+
+fn parse_waveform(s: &str) -> Option<Waveform> {
+    match s {
+        "sine" => Some(Waveform::Sine),
+        "square" => Some(Waveform::Square),
+        "triangle" => Some(Waveform::Triangle),
+        "sawtooth" => Some(Waveform::Sawtooth),
+        "noise" => Some(Waveform::Noise),
+        _ => None,
+    }
+}
+
+fn parse_harmonic(s: &str) -> Option<Harmonic> {
+    let mut parts = s.split(':');
+    let shape = parse_waveform(parts.next()?)?;
+    let amp = parts.next()?.parse().ok()?;
+    let lam = parts.next()?.parse().ok()?;
+    let offset = match parts.next() {
+        Some(offset) => offset.parse().ok()?,
+        None => 0.0,
+    };
+
+    Some(Harmonic {
+        shape,
+        amp,
+        lam,
+        offset,
+    })
+}
+
+fn parse_coord(s: &str) -> Option<Coord> {
+    if let Some(p) = s.strip_suffix('%') {
+        Some(Coord::Percent(p.parse().ok()?))
+    } else {
+        Some(Coord::Pixels(s.parse().ok()?))
+    }
+}
+
+// a centre coordinate, the middle of the image when it's not given
+fn parse_centre(matches: &ArgMatches, name: &str) -> Coord {
+    matches
+        .value_of(name)
+        .map(|c| parse_coord(c).expect("invalid centre"))
+        .unwrap_or(Coord::Percent(50.0))
+}
+
 fn find_parts(filename: &str) -> (&str, &str, usize, usize) {
     let re = regex::Regex::new(r"\[\*+(/\d+)?\]").unwrap();
     let seq = re.find(filename).unwrap();
@@ -222,3 +1270,39 @@ impl<'a> Iterator for FileSeq<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parses(args: &[&str]) -> bool {
+        let args = ["pixel-sort", "in.png", "out.png"].iter().chain(args);
+
+        app().get_matches_from_safe(args).is_ok()
+    }
+
+    #[test]
+    fn regions_need_one_label_map() {
+        assert!(parses(&["--labels", "mask.png", "linear"]));
+        assert!(parses(&["--kmeans", "3", "linear"]));
+        assert!(parses(&["--slic", "16", "linear"]));
+        assert!(parses(&[
+            "--labels",
+            "mask.png",
+            "--region",
+            "#ff0000=linear",
+            "linear"
+        ]));
+        assert!(parses(&[
+            "--kmeans",
+            "3",
+            "--region",
+            "0=--fn hue linear",
+            "linear"
+        ]));
+        assert!(!parses(&["--region", "0=linear", "linear"]));
+        assert!(!parses(&[
+            "--labels", "mask.png", "--kmeans", "3", "linear"
+        ]));
+    }
+}
@@ -0,0 +1,69 @@
+use super::Threshold;
+use image::RgbaImage;
+
+pub fn histogram(image: &RgbaImage, sort_fn: impl Fn(&[u8]) -> u8) -> [u64; 256] {
+    let mut hist = [0; 256];
+
+    for p in image.pixels() {
+        hist[sort_fn(&p.0) as usize] += 1;
+    }
+
+    hist
+}
+
+pub fn threshold(hist: &[u64; 256], threshold: Threshold) -> usize {
+    match threshold {
+        Threshold::Otsu => otsu(hist),
+        Threshold::Percentile(p) => percentile(hist, p),
+    }
+}
+
+// the key that best splits the histogram in two classes, by maximising the variance between them
+pub fn otsu(hist: &[u64; 256]) -> usize {
+    let total = hist.iter().sum::<u64>() as f64;
+    let sum = hist
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| i as f64 * n as f64)
+        .sum::<f64>();
+
+    let (mut below, mut below_sum) = (0.0, 0.0);
+    let (mut best, mut best_var) = (0, 0.0);
+
+    for (i, &n) in hist.iter().enumerate() {
+        below += n as f64;
+        below_sum += i as f64 * n as f64;
+
+        let above = total - below;
+
+        if below == 0.0 || above == 0.0 {
+            continue;
+        }
+
+        let diff = below_sum / below - (sum - below_sum) / above;
+        let var = below * above * diff * diff;
+
+        if var > best_var {
+            best = i + 1;
+            best_var = var;
+        }
+    }
+
+    best.min(255)
+}
+
+pub fn percentile(hist: &[u64; 256], p: f32) -> usize {
+    let total = hist.iter().sum::<u64>();
+    let target = (total as f64 * f64::from(p.clamp(0.0, 100.0)) / 100.0).ceil() as u64;
+    let mut count = 0;
+
+    for (i, &n) in hist.iter().enumerate() {
+        count += n;
+
+        if count >= target.max(1) {
+            return i;
+        }
+    }
+
+    255
+}
@@ -0,0 +1,56 @@
+use super::{Blend, BlendMode};
+use image::{imageops, GrayImage, RgbaImage};
+
+pub fn blend(original: &RgbaImage, sorted: &RgbaImage, blend: &Blend) -> RgbaImage {
+    let (width, height) = sorted.dimensions();
+    let mask = blend
+        .mask
+        .as_ref()
+        .map(|mask| feather(mask, width, height, blend.feather));
+    let mut res = sorted.clone();
+
+    for (x, y, px) in res.enumerate_pixels_mut() {
+        let base = original.get_pixel(x, y);
+        let amount = match &mask {
+            Some(mask) => blend.opacity * f32::from(mask.get_pixel(x, y).0[0]) / 255.0,
+            None => blend.opacity,
+        };
+
+        for c in 0..4 {
+            let a = f32::from(base.0[c]) / 255.0;
+            let b = f32::from(px.0[c]) / 255.0;
+            let mixed = if c == 3 { b } else { mix(blend.mode, a, b) };
+
+            px.0[c] = ((a + (mixed - a) * amount) * 255.0).round() as u8;
+        }
+    }
+
+    res
+}
+
+fn feather(mask: &GrayImage, width: u32, height: u32, radius: f32) -> GrayImage {
+    let mask = if mask.dimensions() != (width, height) {
+        imageops::resize(mask, width, height, imageops::FilterType::Triangle)
+    } else {
+        mask.clone()
+    };
+
+    if radius > 0.0 {
+        imageops::blur(&mask, radius)
+    } else {
+        mask
+    }
+}
+
+fn mix(mode: BlendMode, a: f32, b: f32) -> f32 {
+    match mode {
+        BlendMode::Normal => b,
+        BlendMode::Lighten => a.max(b),
+        BlendMode::Darken => a.min(b),
+        BlendMode::Difference => (a - b).abs(),
+        BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+        BlendMode::Multiply => a * b,
+        BlendMode::Overlay if a < 0.5 => 2.0 * a * b,
+        BlendMode::Overlay => 1.0 - 2.0 * (1.0 - a) * (1.0 - b),
+    }
+}
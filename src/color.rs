@@ -0,0 +1,96 @@
+use super::Component;
+use image::Rgba;
+
+// take `component` from `from`, and everything else from `p`
+pub fn with_component(p: &Rgba<u8>, from: &Rgba<u8>, component: Component) -> Rgba<u8> {
+    if let Component::Alpha = component {
+        return Rgba([p[0], p[1], p[2], from[3]]);
+    }
+
+    let [l, a, b] = to_oklab(p);
+    let [fl, fa, fb] = to_oklab(from);
+    let (c, h) = (a.hypot(b), b.atan2(a));
+    let (fc, fh) = (fa.hypot(fb), fb.atan2(fa));
+
+    let lab = match component {
+        Component::Lightness => [fl, a, b],
+        Component::Hue => [l, c * fh.cos(), c * fh.sin()],
+        Component::Chroma => [l, fc * h.cos(), fc * h.sin()],
+        Component::Alpha => unreachable!(),
+    };
+
+    let [r, g, b] = from_oklab(lab);
+
+    Rgba([r, g, b, p[3]])
+}
+
+// hue in degrees, saturation and lightness from 0 to 1
+pub fn to_hsl(p: &Rgba<u8>) -> (f32, f32, f32) {
+    let [r, g, b] = [p[0], p[1], p[2]].map(|c| f32::from(c) / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let c = max - min;
+    let l = (max + min) / 2.0;
+
+    if c == 0.0 {
+        return (0.0, 0.0, l);
+    }
+
+    let h = if max == r {
+        ((g - b) / c).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / c + 2.0
+    } else {
+        (r - g) / c + 4.0
+    };
+
+    (h * 60.0, c / (1.0 - (2.0 * l - 1.0).abs()), l)
+}
+
+pub fn to_oklab(p: &Rgba<u8>) -> [f64; 3] {
+    let r = to_linear(p[0]);
+    let g = to_linear(p[1]);
+    let b = to_linear(p[2]);
+
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+pub fn from_oklab([l, a, b]: [f64; 3]) -> [u8; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+    [
+        from_linear(4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_),
+        from_linear(-1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_),
+        from_linear(-0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_),
+    ]
+}
+
+fn to_linear(c: u8) -> f64 {
+    let c = f64::from(c) / 255.0;
+
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f64) -> u8 {
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
@@ -0,0 +1,390 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+const CURVE_STEPS: usize = 16;
+
+pub type Paths = Vec<Vec<(f64, f64)>>;
+
+// the `x, y, width, height` of the space paths are drawn in, like an svg viewBox
+pub type Space = (f64, f64, f64, f64);
+
+pub fn load(file: impl AsRef<Path>) -> Result<(Paths, Option<Space>)> {
+    let file = file.as_ref();
+    let text = std::fs::read_to_string(file)?;
+    let ext = file
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "svg" => parse_svg(&text),
+        "json" => Ok((parse_json(&text)?, None)),
+        "csv" | "txt" => Ok((parse_csv(&text)?, None)),
+        _ => Err(invalid(format!("unknown path file `{}`", file.display()))),
+    }
+}
+
+pub fn parse_svg(text: &str) -> Result<(Paths, Option<Space>)> {
+    let attr =
+        |name: &str| regex::Regex::new(&format!(r#"\s{}\s*=\s*["']([^"']*)["']"#, name)).unwrap();
+
+    let svg = regex::Regex::new(r"<svg[^>]*>")
+        .unwrap()
+        .find(text)
+        .map(|m| m.as_str())
+        .unwrap_or("");
+
+    let number = |s: &str| {
+        s.trim()
+            .trim_end_matches("px")
+            .parse::<f64>()
+            .map_err(|_| invalid(format!("invalid size `{}`", s)))
+    };
+
+    let space = if let Some(view_box) = attr("viewBox").captures(svg) {
+        let values = numbers(&view_box[1])?;
+
+        match values[..] {
+            [x, y, w, h] => Some(space(x, y, w, h)?),
+            _ => return Err(invalid("invalid viewBox")),
+        }
+    } else if let (Some(w), Some(h)) = (attr("width").captures(svg), attr("height").captures(svg)) {
+        Some(space(0.0, 0.0, number(&w[1])?, number(&h[1])?)?)
+    } else {
+        None
+    };
+
+    let mut paths = Vec::new();
+
+    for d in attr("d").captures_iter(text) {
+        paths.extend(parse_path_data(&d[1])?);
+    }
+
+    Ok((paths, space))
+}
+
+// paths are scaled by the image size over the space's, so it needs a finite size above zero
+pub fn space(x: f64, y: f64, w: f64, h: f64) -> Result<Space> {
+    if [x, y, w, h].iter().all(|v| v.is_finite()) && w > 0.0 && h > 0.0 {
+        Ok((x, y, w, h))
+    } else {
+        Err(invalid(format!("invalid space `{} {} {} {}`", x, y, w, h)))
+    }
+}
+
+pub fn parse_path_data(d: &str) -> Result<Paths> {
+    let mut tokens = tokenize(d)?.into_iter().peekable();
+    let mut paths: Paths = Vec::new();
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    let mut control: Option<(f64, f64)> = None;
+    let mut cmd = None;
+
+    while let Some(token) = tokens.peek().cloned() {
+        let c = match token {
+            Token::Command(c) => {
+                tokens.next();
+                c
+            }
+            Token::Number(_) => match cmd {
+                Some('M') => 'L',
+                Some('m') => 'l',
+                Some(c) if c != 'Z' && c != 'z' => c,
+                _ => return Err(invalid(format!("expected a command in `{}`", d))),
+            },
+        };
+
+        let mut num = || match tokens.next() {
+            Some(Token::Number(n)) => Ok(n),
+            _ => Err(invalid(format!("expected a number in `{}`", d))),
+        };
+
+        let rel = c.is_ascii_lowercase();
+        let base = if rel { current } else { (0.0, 0.0) };
+        let point = |num: &mut dyn FnMut() -> Result<f64>| -> Result<(f64, f64)> {
+            Ok((base.0 + num()?, base.1 + num()?))
+        };
+
+        let reflect = |prev: Option<(f64, f64)>, kinds: &[char]| match (prev, cmd) {
+            (Some(p), Some(last)) if kinds.contains(&last.to_ascii_uppercase()) => {
+                (2.0 * current.0 - p.0, 2.0 * current.1 - p.1)
+            }
+            _ => current,
+        };
+
+        let mut next_control = None;
+
+        match c.to_ascii_uppercase() {
+            'M' => {
+                current = point(&mut num)?;
+                start = current;
+                paths.push(vec![current]);
+            }
+            'L' => {
+                current = point(&mut num)?;
+                line_to(&mut paths, current);
+            }
+            'H' => {
+                current.0 = base.0 + num()?;
+                line_to(&mut paths, current);
+            }
+            'V' => {
+                current.1 = base.1 + num()?;
+                line_to(&mut paths, current);
+            }
+            'C' | 'S' => {
+                let c1 = if c.eq_ignore_ascii_case(&'C') {
+                    point(&mut num)?
+                } else {
+                    reflect(control, &['C', 'S'])
+                };
+
+                let c2 = point(&mut num)?;
+                let end = point(&mut num)?;
+                let p0 = current;
+
+                for i in 1..=CURVE_STEPS {
+                    let t = i as f64 / CURVE_STEPS as f64;
+                    let u = 1.0 - t;
+                    let at = |a: f64, b: f64, c: f64, d: f64| {
+                        u * u * u * a + 3.0 * u * u * t * b + 3.0 * u * t * t * c + t * t * t * d
+                    };
+
+                    line_to(
+                        &mut paths,
+                        (at(p0.0, c1.0, c2.0, end.0), at(p0.1, c1.1, c2.1, end.1)),
+                    );
+                }
+
+                current = end;
+                next_control = Some(c2);
+            }
+            'Q' | 'T' => {
+                let c1 = if c.eq_ignore_ascii_case(&'Q') {
+                    point(&mut num)?
+                } else {
+                    reflect(control, &['Q', 'T'])
+                };
+
+                let end = point(&mut num)?;
+                let p0 = current;
+
+                for i in 1..=CURVE_STEPS {
+                    let t = i as f64 / CURVE_STEPS as f64;
+                    let u = 1.0 - t;
+                    let at = |a: f64, b: f64, c: f64| u * u * a + 2.0 * u * t * b + t * t * c;
+
+                    line_to(&mut paths, (at(p0.0, c1.0, end.0), at(p0.1, c1.1, end.1)));
+                }
+
+                current = end;
+                next_control = Some(c1);
+            }
+            'A' => {
+                let (rx, ry, rotation) = (num()?, num()?, num()?);
+                let (large, sweep) = (num()? != 0.0, num()? != 0.0);
+                let end = point(&mut num)?;
+
+                for p in arc(current, end, rx, ry, rotation, large, sweep) {
+                    line_to(&mut paths, p);
+                }
+
+                current = end;
+            }
+            'Z' => {
+                line_to(&mut paths, start);
+                current = start;
+            }
+            _ => return Err(invalid(format!("unknown command `{}` in `{}`", c, d))),
+        }
+
+        control = next_control;
+        cmd = Some(c);
+    }
+
+    Ok(paths)
+}
+
+pub fn parse_csv(text: &str) -> Result<Paths> {
+    let mut paths = vec![Vec::new()];
+
+    for line in text.lines() {
+        let values = line
+            .split([',', ';', '\t'])
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<std::result::Result<Vec<_>, _>>();
+
+        match values.as_deref() {
+            Ok([x, y]) => paths.last_mut().unwrap().push((*x, *y)),
+            // blank lines and headers start a new polyline
+            _ => paths.push(Vec::new()),
+        }
+    }
+
+    paths.retain(|p| !p.is_empty());
+
+    Ok(paths)
+}
+
+pub fn parse_json(text: &str) -> Result<Paths> {
+    let mut paths = Vec::new();
+    let mut path = Vec::new();
+    let mut point = Vec::new();
+    let mut depth = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                match depth {
+                    3 => match point[..] {
+                        [x, y] => path.push((x, y)),
+                        _ => return Err(invalid("a point must have two coordinates")),
+                    },
+                    2 => paths.push(std::mem::take(&mut path)),
+                    _ => {}
+                }
+
+                point.clear();
+                depth -= 1;
+            }
+            ',' | ' ' | '\t' | '\r' | '\n' => {}
+            _ if depth == 3 => {
+                let mut end = i + c.len_utf8();
+
+                while let Some(&(j, c)) = chars.peek() {
+                    if c == ',' || c == ']' || c.is_whitespace() {
+                        break;
+                    }
+
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+
+                point.push(
+                    text[i..end]
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid number `{}`", &text[i..end])))?,
+                );
+            }
+            _ => {
+                return Err(invalid(
+                    "expected a list of polylines `[[[x, y], ...], ...]`",
+                ))
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Command(char),
+    Number(f64),
+}
+
+fn tokenize(d: &str) -> Result<Vec<Token>> {
+    let re = regex::Regex::new(r"[A-Za-z]|[-+]?(\d+\.?\d*|\.\d+)([eE][-+]?\d+)?").unwrap();
+    let mut tokens = Vec::new();
+    let mut last = 0;
+
+    for m in re.find_iter(d) {
+        if !d[last..m.start()]
+            .chars()
+            .all(|c| c.is_whitespace() || c == ',')
+        {
+            return Err(invalid(format!("invalid path data `{}`", d)));
+        }
+
+        let s = m.as_str();
+
+        tokens.push(match s.parse() {
+            Ok(n) => Token::Number(n),
+            Err(_) => Token::Command(s.chars().next().unwrap()),
+        });
+
+        last = m.end();
+    }
+
+    Ok(tokens)
+}
+
+fn numbers(s: &str) -> Result<Vec<f64>> {
+    tokenize(s)?
+        .into_iter()
+        .map(|t| match t {
+            Token::Number(n) => Ok(n),
+            Token::Command(c) => Err(invalid(format!("unexpected `{}`", c))),
+        })
+        .collect()
+}
+
+fn line_to(paths: &mut Paths, p: (f64, f64)) {
+    match paths.last_mut() {
+        Some(path) => path.push(p),
+        None => paths.push(vec![(0.0, 0.0), p]),
+    }
+}
+
+fn arc(
+    from: (f64, f64),
+    to: (f64, f64),
+    rx: f64,
+    ry: f64,
+    rotation: f64,
+    large: bool,
+    sweep: bool,
+) -> Vec<(f64, f64)> {
+    use std::f64::consts::TAU;
+
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+
+    if rx == 0.0 || ry == 0.0 || from == to {
+        return vec![to];
+    }
+
+    // endpoint to centre parameterization, see the svg spec's implementation notes
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let (hx, hy) = ((from.0 - to.0) / 2.0, (from.1 - to.1) / 2.0);
+    let (x1, y1) = (cos * hx + sin * hy, -sin * hx + cos * hy);
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let sign = if large == sweep { -1.0 } else { 1.0 };
+    let coef = sign * (num / den).max(0.0).sqrt();
+    let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+    let (cx, cy) = (
+        cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.0,
+        sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.0,
+    );
+
+    let theta = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let mut delta = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx) - theta;
+
+    if sweep && delta < 0.0 {
+        delta += TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= TAU;
+    }
+
+    (1..=CURVE_STEPS)
+        .map(|i| {
+            let a = theta + delta * i as f64 / CURVE_STEPS as f64;
+            let (x, y) = (rx * a.cos(), ry * a.sin());
+
+            (cos * x - sin * y + cx, sin * x + cos * y + cy)
+        })
+        .collect()
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
@@ -1,15 +1,14 @@
 use super::*;
-use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Rgba, RgbaImage};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::borrow::Borrow;
+use std::ops::Range;
 use std::path::Path;
 
 fn calc_steps(opts: &Opts) -> u64 {
     let mut steps = 3;
 
-    if opts.vertical {
-        steps += 2;
-    }
-
     if opts.internal_scale.is_some() {
         steps += 2;
     }
@@ -53,78 +52,36 @@ pub fn process_image(input: impl AsRef<Path>, output: impl AsRef<Path>, opts: Op
     let thread = std::thread::spawn(move || {
         pb.set_message("Reading");
 
-        let mut image = image::open(input).unwrap();
-        let mut resize = opts.resize;
-        let (iw, ih) = image.dimensions();
+        let image = image::open(input).unwrap();
 
-        if opts.vertical {
+        if opts.internal_scale.is_some() || opts.resize.is_some() {
             pbo.inc(1);
-            pb.set_message("Rotating");
-            image = image.rotate90();
+            pb.set_message("Resizing");
         }
 
-        if let Some(scale) = opts.internal_scale {
-            pbo.inc(1);
-            resize = resize.or(Some(Scale::Pixels(iw, ih)));
-
-            let (sw, sh) = scale.calc(iw, ih);
-
-            if sw != iw || sh != ih {
-                pb.set_message("Resizing");
-
-                if opts.vertical {
-                    image = image.resize_exact(sh, sw, image::imageops::FilterType::Triangle);
-                } else {
-                    image = image.resize_exact(sw, sh, image::imageops::FilterType::Triangle);
-                }
-            }
-        } else if let Some(scale) = opts.resize {
-            pbo.inc(1);
-            resize = None;
-
-            let (sw, sh) = scale.calc(iw, ih);
-
-            if sw != iw || sh != ih {
-                pb.set_message("Resizing");
-
-                if opts.vertical {
-                    image = image.resize_exact(sh, sw, image::imageops::FilterType::Triangle);
-                } else {
-                    image = image.resize_exact(sw, sh, image::imageops::FilterType::Triangle);
-                }
-            }
-        }
+        let (image, size) = scale_input(image, &opts);
 
         pbo.inc(1);
         pb.set_length(0);
         pb.set_style(bar_style);
         pb.set_message("Sorting");
 
-        let mut res = sort_image(&pb, image, &opts);
+        let mut res = match &opts.debug_intervals {
+            Some(path) => {
+                let (res, debug) = sort_image_debug(&pb, image, &opts, 0);
 
-        pb.set_style(dots_style);
-
-        if let Some(scale) = resize {
-            pbo.inc(1);
-
-            let (nw, nh) = res.dimensions();
-            let (sw, sh) = scale.calc(iw, ih);
-
-            if sw != nw || sh != nh {
-                pb.set_message("Resizing");
-
-                if opts.vertical {
-                    res = res.resize_exact(sh, sw, image::imageops::FilterType::Lanczos3);
-                } else {
-                    res = res.resize_exact(sw, sh, image::imageops::FilterType::Lanczos3);
-                }
+                debug.save(path).unwrap();
+                res
             }
-        }
+            None => sort_image(&pb, image, &opts, 0),
+        };
+
+        pb.set_style(dots_style);
 
-        if opts.vertical {
+        if let Some(size) = size {
             pbo.inc(1);
-            pb.set_message("Rotating");
-            res = res.rotate270();
+            pb.set_message("Resizing");
+            res = resize_to(res, size, image::imageops::FilterType::Lanczos3);
         }
 
         pbo.inc(1);
@@ -142,397 +99,907 @@ pub fn process_image(input: impl AsRef<Path>, output: impl AsRef<Path>, opts: Op
     }
 }
 
-pub fn sort_image(pb: &ProgressBar, image: DynamicImage, opts: &Opts) -> DynamicImage {
+// the same steps as `process_image` without reading, saving or progress bars, for sorting many frames
+pub fn sort_scaled(image: DynamicImage, opts: &Opts, seed: u64) -> DynamicImage {
+    let (image, size) = scale_input(image, opts);
+    let res = sort_image(&ProgressBar::hidden(), image, opts, seed);
+
+    match size {
+        Some(size) => resize_to(res, size, image::imageops::FilterType::Lanczos3),
+        None => res,
+    }
+}
+
+// apply `--internal-scale` or `--resize`, and return the size to scale the sorted result back to
+fn scale_input(image: DynamicImage, opts: &Opts) -> (DynamicImage, Option<(u32, u32)>) {
+    let (iw, ih) = image.dimensions();
+
+    let (scale, size) = match (opts.internal_scale, opts.resize) {
+        (Some(scale), resize) => (scale, Some(resize.unwrap_or(Scale::Pixels(iw, ih)))),
+        (None, Some(resize)) => (resize, None),
+        (None, None) => return (image, None),
+    };
+
+    let image = resize_to(
+        image,
+        scale.calc(iw, ih),
+        image::imageops::FilterType::Triangle,
+    );
+
+    (image, size.map(|size| size.calc(iw, ih)))
+}
+
+fn resize_to(
+    image: DynamicImage,
+    (w, h): (u32, u32),
+    filter: image::imageops::FilterType,
+) -> DynamicImage {
+    if image.dimensions() == (w, h) {
+        image
+    } else {
+        image.resize_exact(w, h, filter)
+    }
+}
+
+// `seed` drives the random choices, so the same image sorted with the same seed always gives the same result
+pub fn sort_image(pb: &ProgressBar, image: DynamicImage, opts: &Opts, seed: u64) -> DynamicImage {
+    sort(pb, image, opts, seed, None)
+}
+
+// also draw the spans that get sorted along every path in alternating colours, over a grey copy of the image
+pub fn sort_image_debug(
+    pb: &ProgressBar,
+    image: DynamicImage,
+    opts: &Opts,
+    seed: u64,
+) -> (DynamicImage, RgbaImage) {
+    let mut debug = image.to_luma8();
+
+    for p in debug.pixels_mut() {
+        p.0[0] = (u16::from(p.0[0]) * 2 / 5) as u8;
+    }
+
+    let mut debug = DynamicImage::ImageLuma8(debug).to_rgba8();
+    let res = sort(pb, image, opts, seed, Some(&mut debug));
+
+    (res, debug)
+}
+
+fn sort(
+    pb: &ProgressBar,
+    image: DynamicImage,
+    opts: &Opts,
+    seed: u64,
+    mut debug: Option<&mut RgbaImage>,
+) -> DynamicImage {
+    let rng = &mut StdRng::seed_from_u64(seed);
     let mut rgba = image.to_rgba8();
+    let opts = &resolve_opts(&rgba, opts);
+    let original = opts.blend.as_ref().map(|_| rgba.clone());
     let (width, height) = rgba.dimensions();
 
-    match opts.sort_type {
-        SortType::Spiral => {
-            pb.set_length(u64::from(height / 2));
-            pb.tick();
-
-            let rgba_c = rgba.clone();
-
-            for i in 0..height / 2 {
-                let top = ((i + 1)..width - i).map(|x| (x, i));
-                let right = ((i + 1)..height - i).map(|y| (width - i - 1, y));
-                let bottom = (i..width - i - 1).map(|x| (x, height - i - 1)).rev();
-                let left = (i..height - i - 1).map(|y| (i, y)).rev();
-                let idxs = top
-                    .chain(right)
-                    .chain(bottom)
-                    .chain(left)
-                    .collect::<Vec<_>>();
-
-                let mut pixels = idxs
-                    .iter()
-                    .map(|(x, y)| rgba_c.get_pixel(*x, *y))
-                    .collect::<Vec<_>>();
-
-                sort_pixels(opts, &mut pixels[..], opts.sort_fn);
-
-                for ((x, y), px) in idxs.into_iter().zip(pixels) {
-                    rgba.put_pixel(x, y, *px);
-                }
+    match (opts.block, &opts.segments) {
+        (Some(block), _) => sort_blocks(pb, &mut rgba, block, opts, rng, debug),
+        (None, Some(segments)) => {
+            let runs = path::cover(width, height, paths(&rgba, opts));
+            let labels = segment::labels(&rgba, segments);
+            let label = |run: &Vec<(u32, u32)>| labels[(run[0].1 * width + run[0].0) as usize];
+            let runs = segment::split(runs, &labels, width)
+                .into_iter()
+                .filter(|run| opts.regions.iter().all(|r| r.label != label(run)))
+                .collect::<Vec<_>>();
+
+            // find every region's paths before sorting anything, as some paths follow the image
+            let regions = opts
+                .regions
+                .iter()
+                .map(|region| {
+                    let runs = path::cover(width, height, paths(&rgba, &region.opts));
+                    let runs = segment::split(runs, &labels, width)
+                        .into_iter()
+                        .filter(|run| label(run) == region.label)
+                        .collect::<Vec<_>>();
+
+                    (runs, &region.opts)
+                })
+                .collect::<Vec<_>>();
+
+            sort_runs(pb, &mut rgba, &runs, opts, rng, debug.as_deref_mut());
 
-                pb.inc(1);
+            for (runs, opts) in regions {
+                sort_runs(pb, &mut rgba, &runs, opts, rng, debug.as_deref_mut());
             }
         }
+        (None, None) => {
+            let runs = path::cover(width, height, paths(&rgba, opts));
+
+            sort_runs(pb, &mut rgba, &runs, opts, rng, debug);
+        }
+    }
+
+    if let (Some(b), Some(original)) = (&opts.blend, &original) {
+        rgba = blend::blend(original, &rgba, b);
+    }
+
+    DynamicImage::ImageRgba8(rgba)
+}
+
+fn paths(rgba: &RgbaImage, opts: &Opts) -> Vec<Vec<(u32, u32)>> {
+    let (width, height) = rgba.dimensions();
+    let angle = if opts.vertical {
+        opts.angle - 90.0
+    } else {
+        opts.angle
+    };
+
+    match &opts.sort_type {
+        SortType::Linear => path::linear(width, height, angle),
+        SortType::Spiral => path::spiral(width, height, angle),
+        SortType::Polygon {
+            cx,
+            cy,
+            sides,
+            rotation,
+            star,
+            fit,
+        } => path::polygon(
+            width,
+            height,
+            (f64::from(cx.calc(width)), f64::from(cy.calc(height))),
+            *sides,
+            *rotation,
+            *star,
+            *fit,
+            angle,
+        ),
+        SortType::Diagonal { anti } => path::diagonal(width, height, *anti),
+        SortType::Zigzag => path::zigzag(width, height, opts.vertical),
         SortType::Circle { cx, cy } => {
-            let dist = |x: u32, y: u32| {
-                ((x as f64 - cx as f64).powi(2) + (y as f64 - cy as f64).powi(2)).sqrt()
+            path::circle(width, height, cx.calc(width), cy.calc(height), angle)
+        }
+        SortType::Ellipse {
+            cx,
+            cy,
+            ratio,
+            rotation,
+        } => path::ellipse(
+            width,
+            height,
+            (cx.calc(width), cy.calc(height)),
+            *ratio,
+            *rotation,
+            angle,
+        ),
+        SortType::MultiCircle { centres } => {
+            let centres = centres
+                .iter()
+                .map(|(cx, cy)| (cx.calc(width), cy.calc(height)))
+                .collect::<Vec<_>>();
+
+            path::multi_circle(width, height, &centres, angle)
+        }
+        SortType::Swirl {
+            cx,
+            cy,
+            shape,
+            arms,
+            clockwise,
+        } => path::swirl(
+            width,
+            height,
+            (cx.calc(width), cy.calc(height)),
+            *shape,
+            *arms,
+            *clockwise,
+            angle,
+        ),
+        SortType::Radial {
+            cx,
+            cy,
+            rays,
+            inward,
+        } => path::radial(
+            width,
+            height,
+            (cx.calc(width), cy.calc(height)),
+            *rays,
+            *inward,
+            angle,
+        ),
+        SortType::FlowField {
+            scale,
+            octaves,
+            seed,
+            length,
+        } => {
+            let noise = noise::Perlin::new(*seed);
+            let angle = angle.to_radians();
+
+            path::streamlines(width, height, *length, |x, y| {
+                noise.fbm(x / scale, y / scale, *octaves) * std::f64::consts::TAU * 2.0 + angle
+            })
+        }
+        SortType::Contour {
+            along,
+            smooth,
+            length,
+        } => path::contour(rgba, *along, *smooth, *length, angle),
+        SortType::Custom { paths, space } => path::polylines(width, height, paths, *space),
+        SortType::Sine { amp, lam, offset } => {
+            let sine = Harmonic {
+                shape: Waveform::Sine,
+                amp: *amp,
+                lam: *lam,
+                offset: *offset,
             };
 
-            let n = dist(0, 0)
-                .max(dist(0, height))
-                .max(dist(width, 0))
-                .max(dist(width, height))
-                .ceil() as u32;
+            path::wave(width, height, &[sine], 0.0, angle)
+        }
+        SortType::Wave { harmonics, growth } => {
+            path::wave(width, height, harmonics, *growth, angle)
+        }
+    }
+}
 
-            pb.set_length(n as u64);
-            pb.tick();
+// fill in what depends on the image being sorted
+fn resolve_opts(rgba: &RgbaImage, opts: &Opts) -> Opts {
+    let mut opts = opts.clone();
+    let (width, height) = rgba.dimensions();
 
-            let rgba_c = rgba.clone();
-            let mut written = vec![vec![false; height as usize]; width as usize];
+    if let IntervalType::Map { map, .. } = &mut opts.interval {
+        if map.dimensions() != (width, height) {
+            *map =
+                image::imageops::resize(map, width, height, image::imageops::FilterType::Triangle);
+        }
+    }
 
-            for i in 0..n {
-                let idxs = circle_points(&rgba_c, cx, cy, i, opts.angle);
-                let mut pixels = idxs
-                    .iter()
-                    .map(|(x, y)| rgba_c.get_pixel(*x, *y))
-                    .collect::<Vec<_>>();
+    if opts.auto_min.is_some() || opts.auto_max.is_some() {
+        let hist = analyze::histogram(rgba, opts.sort_fn);
 
-                sort_pixels(opts, &mut pixels[..], opts.sort_fn);
+        if let Some(min) = opts.auto_min.take() {
+            opts.min = analyze::threshold(&hist, min);
+        }
 
-                for ((x, y), px) in idxs.into_iter().zip(pixels) {
-                    rgba.put_pixel(x, y, *px);
-                    written[x as usize][y as usize] = true;
-                }
+        // otsu's threshold is the first key of the upper class, so `max` stops just below it
+        if let Some(max) = opts.auto_max.take() {
+            opts.max = match max {
+                Threshold::Otsu => analyze::otsu(&hist).saturating_sub(1),
+                _ => analyze::threshold(&hist, max),
+            };
+        }
 
-                pb.inc(1);
-            }
+        // e.g. `--min auto --max auto` leaves an empty window, which would sort nothing
+        if opts.min > opts.max {
+            panic!(
+                "--min resolved to {} which is above --max {}",
+                opts.min, opts.max
+            );
+        }
+    }
 
-            let rgba_c = rgba.clone();
-            let color_of_neighbours = |x: i32, y: i32| {
-                let neighbours = [
-                    (x - 1, y - 1),
-                    (x, y - 1),
-                    (x + 1, y - 1),
-                    (x - 1, y),
-                    (x + 1, y),
-                    (x - 1, y + 1),
-                    (x, y + 1),
-                    (x + 1, y + 1),
-                ]
-                .iter()
-                .filter(|(x, _)| (0..written.len() as i32).contains(x))
-                .filter(|(x, y)| (0..written[*x as usize].len() as i32).contains(y))
-                .filter(|(x, y)| written[*x as usize][*y as usize])
-                .map(|(x, y)| rgba_c.get_pixel(*x as u32, *y as u32))
-                .collect::<Vec<_>>();
+    for region in &mut opts.regions {
+        region.opts = resolve_opts(rgba, &region.opts);
+    }
 
-                let mut avg = Rgba([0.0, 0.0, 0.0, 0.0]);
-                let len = neighbours.len() as f64;
+    opts
+}
 
-                for pixel in neighbours {
-                    avg.0[0] += pixel.0[0] as f64;
-                    avg.0[1] += pixel.0[1] as f64;
-                    avg.0[2] += pixel.0[2] as f64;
-                    avg.0[3] += pixel.0[3] as f64;
-                }
+// sort whole tiles along the paths through a grid of tiles, leaving the remainder at the edges in place
+fn sort_blocks(
+    pb: &ProgressBar,
+    rgba: &mut RgbaImage,
+    block: Block,
+    opts: &Opts,
+    rng: &mut StdRng,
+    mut debug: Option<&mut RgbaImage>,
+) {
+    let (width, height) = rgba.dimensions();
+    let (bw, bh) = (block.width.max(1), block.height.max(1));
+    let (gw, gh) = (width / bw, height / bh);
 
-                avg.0[0] /= len;
-                avg.0[1] /= len;
-                avg.0[2] /= len;
-                avg.0[3] /= len;
-
-                Rgba([
-                    avg.0[0] as u8,
-                    avg.0[1] as u8,
-                    avg.0[2] as u8,
-                    avg.0[3] as u8,
-                ])
-            };
+    if gw == 0 || gh == 0 {
+        return;
+    }
 
-            for (x, col) in written.iter().enumerate() {
-                for (y, val) in col.iter().enumerate() {
-                    if !*val {
-                        let col = color_of_neighbours(x as i32, y as i32);
+    let source = rgba.clone();
+    let summary = RgbaImage::from_fn(gw, gh, |x, y| {
+        let tile = source.view(x * bw, y * bh, bw, bh);
 
-                        rgba.put_pixel(x as u32, y as u32, col);
-                    }
-                }
-            }
+        summarise(tile.pixels().map(|(_, _, p)| p), block.key, opts)
+    });
+
+    let runs = path::cover(
+        gw,
+        gh,
+        paths(&summary, &tile_opts(opts, (width, height), (bw, bh))),
+    );
+
+    pb.inc_length(runs.len() as u64);
+    pb.tick();
+
+    for idxs in &runs {
+        let mut tiles = idxs
+            .iter()
+            .map(|&(x, y)| Tile {
+                key: *summary.get_pixel(x, y),
+                pos: (x, y),
+            })
+            .collect::<Vec<_>>();
+
+        let corners = idxs
+            .iter()
+            .map(|(x, y)| (x * bw, y * bh))
+            .collect::<Vec<_>>();
+
+        let spans = spans(opts, &tiles, &corners, rng);
+
+        if let Some(debug) = debug.as_deref_mut() {
+            draw_spans(debug, idxs, &spans, opts, None, (bw, bh));
         }
-        SortType::Sine { amp, lam, offset } => {
-            let (c_x, c_y, diag) = (
-                (width as f64 * 0.5).floor(),
-                (height as f64 * 0.5).floor(),
-                (width as f64).hypot(height as f64).floor() as u32,
-            );
 
-            pb.set_length(u64::from(diag * 3));
-            pb.tick();
-
-            let ang = opts.angle.to_radians();
-            let (sin, cos) = (ang.sin(), ang.cos());
-            let rgba_c = rgba.clone();
-
-            for y in 0..(diag * 3) {
-                let idxs = (0..diag)
-                    .map(|x| x as f64)
-                    .map(|x| (x, y as f64 / 3.0 + (x / lam + offset).sin() * amp))
-                    .map(|(x, y)| (x - diag as f64 / 2.0, y - diag as f64 / 2.0))
-                    .map(|(x, y)| (x * cos - y * sin, y * cos + x * sin))
-                    .map(|(x, y)| (x + c_x, y + c_y))
-                    .filter_map(|(x, y)| {
-                        if x >= 0.0 && x < width as f64 && y > -0.0 && y < height as f64 {
-                            Some((x.floor() as u32, y.floor() as u32))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>();
+        sort_spans(&mut tiles[..], &spans, opts.sort_fn);
 
-                let mut pixels = idxs
-                    .iter()
-                    .map(|(x, y)| rgba_c.get_pixel(*x, *y))
-                    .collect::<Vec<_>>();
+        for ((x, y), tile) in idxs.iter().zip(tiles) {
+            let view = source.view(tile.pos.0 * bw, tile.pos.1 * bh, bw, bh);
 
-                sort_pixels(opts, &mut pixels[..], opts.sort_fn);
+            rgba.copy_from(&view, x * bw, y * bh).unwrap();
+        }
 
-                for ((x, y), px) in idxs.into_iter().zip(pixels) {
-                    rgba.put_pixel(x, y, *px);
-                }
+        pb.inc(1);
+    }
+}
 
-                pb.inc(1);
+// the paths run over the grid of tiles, so everything given in pixels shrinks by the size of a tile
+fn tile_opts(opts: &Opts, (width, height): (u32, u32), (bw, bh): (u32, u32)) -> Opts {
+    let scale = f64::from(bw + bh) / 2.0;
+    let shrink = |c: &mut Coord, size: u32| {
+        if let Coord::Pixels(p) = c {
+            *p /= size;
+        }
+    };
+    let shorten = |length: &mut usize| *length = (*length as f64 / scale).ceil() as usize;
+    let mut opts = opts.clone();
+
+    match &mut opts.sort_type {
+        SortType::Polygon { cx, cy, .. }
+        | SortType::Circle { cx, cy }
+        | SortType::Ellipse { cx, cy, .. }
+        | SortType::Radial { cx, cy, .. } => {
+            shrink(cx, bw);
+            shrink(cy, bh);
+        }
+        SortType::MultiCircle { centres } => {
+            for (cx, cy) in centres {
+                shrink(cx, bw);
+                shrink(cy, bh);
             }
         }
-        SortType::Linear if opts.angle != 0.0 => {
-            let tan = opts.angle.to_radians().tan();
-            let extra_height = (tan * width as f64).floor() as i64;
-            let range = if extra_height > 0 {
-                -extra_height..i64::from(height)
-            } else {
-                0..(i64::from(height) - extra_height)
-            };
+        SortType::Swirl { cx, cy, shape, .. } => {
+            shrink(cx, bw);
+            shrink(cy, bh);
 
-            pb.set_length((range.end - range.start) as u64);
-            pb.tick();
+            if let SpiralShape::Archimedean { spacing } = shape {
+                *spacing /= scale;
+            }
+        }
+        SortType::FlowField {
+            scale: s, length, ..
+        } => {
+            *s /= scale;
+            shorten(length);
+        }
+        SortType::Contour { smooth, length, .. } => {
+            *smooth /= scale as f32;
+            shorten(length);
+        }
+        // custom paths are drawn over the whole image unless they bring their own space
+        SortType::Custom { space, .. } => {
+            space.get_or_insert((0.0, 0.0, f64::from(width), f64::from(height)));
+        }
+        SortType::Sine { amp, lam, .. } => {
+            *amp /= scale;
+            *lam /= scale;
+        }
+        SortType::Wave { harmonics, .. } => {
+            for h in harmonics {
+                h.amp /= scale;
+                h.lam /= scale;
+            }
+        }
+        SortType::Linear | SortType::Spiral | SortType::Diagonal { .. } | SortType::Zigzag => {}
+    }
 
-            let rgba_c = rgba.clone();
+    opts
+}
 
-            for y in range {
-                let idxs = (0..width)
-                    .map(|xv| (xv, (xv as f64 * tan + y as f64) as u32))
-                    .filter(|(_, y)| *y > 0 && *y < height)
-                    .collect::<Vec<_>>();
+struct Tile {
+    key: Rgba<u8>,
+    pos: (u32, u32),
+}
 
-                let mut pixels = idxs
-                    .iter()
-                    .map(|(x, y)| rgba_c.get_pixel(*x, *y))
-                    .collect::<Vec<_>>();
+impl Borrow<Rgba<u8>> for Tile {
+    fn borrow(&self) -> &Rgba<u8> {
+        &self.key
+    }
+}
 
-                sort_pixels(opts, &mut pixels[..], opts.sort_fn);
+fn summarise(pixels: impl Iterator<Item = Rgba<u8>>, key: BlockKey, opts: &Opts) -> Rgba<u8> {
+    match key {
+        BlockKey::Mean => {
+            let mut sum = [0u64; 4];
+            let mut n = 0;
 
-                for ((x, y), px) in idxs.iter().zip(pixels.iter()) {
-                    rgba.put_pixel(*x, *y, **px);
+            for p in pixels {
+                for (s, c) in sum.iter_mut().zip(p.0) {
+                    *s += u64::from(c);
                 }
 
-                pb.inc(1);
+                n += 1;
             }
+
+            Rgba(sum.map(|s| (s / n.max(1)) as u8))
         }
-        SortType::Linear => {
-            pb.set_length(height as u64);
-            pb.tick();
+        BlockKey::Median => {
+            let mut pixels = pixels.collect::<Vec<_>>();
+            let mid = pixels.len() / 2;
 
-            for (y, row) in rgba
-                .clone()
-                .pixels()
-                .collect::<Vec<_>>()
-                .chunks_mut(width as usize)
-                .enumerate()
-            {
-                sort_pixels(opts, &mut row[..], opts.sort_fn);
+            pixels.select_nth_unstable_by_key(mid, |p| (opts.sort_fn)(&p.0));
+            pixels[mid]
+        }
+    }
+}
 
-                for (x, px) in row.iter().enumerate() {
-                    rgba.put_pixel(x as u32, y as u32, **px);
-                }
+pub fn is_permutation(input: &DynamicImage, output: &DynamicImage) -> bool {
+    if input.dimensions() != output.dimensions() {
+        return false;
+    }
+
+    let mut input = input.to_rgba8().pixels().map(|p| p.0).collect::<Vec<_>>();
+    let mut output = output.to_rgba8().pixels().map(|p| p.0).collect::<Vec<_>>();
+
+    input.sort_unstable();
+    output.sort_unstable();
+    input == output
+}
+
+fn sort_runs(
+    pb: &ProgressBar,
+    rgba: &mut RgbaImage,
+    runs: &[Vec<(u32, u32)>],
+    opts: &Opts,
+    rng: &mut StdRng,
+    mut debug: Option<&mut RgbaImage>,
+) {
+    pb.inc_length(runs.len() as u64);
+    pb.tick();
+
+    let rgba_c = rgba.clone();
+
+    for idxs in runs {
+        if opts.channels.is_empty() {
+            let mut pixels = idxs
+                .iter()
+                .map(|(x, y)| rgba_c.get_pixel(*x, *y))
+                .collect::<Vec<_>>();
+
+            let spans = spans(opts, &pixels, idxs, rng);
 
-                pb.inc(1);
+            if let Some(debug) = debug.as_deref_mut() {
+                draw_spans(debug, idxs, &spans, opts, None, (1, 1));
+            }
+
+            sort_spans(&mut pixels[..], &spans, opts.sort_fn);
+
+            for ((x, y), px) in idxs.iter().zip(pixels) {
+                match opts.component {
+                    Some(component) => {
+                        let own = rgba_c.get_pixel(*x, *y);
+
+                        rgba.put_pixel(*x, *y, color::with_component(own, px, component));
+                    }
+                    None => rgba.put_pixel(*x, *y, *px),
+                }
+            }
+        } else {
+            for &c in &opts.channels {
+                sort_channel(rgba, &rgba_c, idxs, c, opts, rng, debug.as_deref_mut());
             }
         }
+
+        pb.inc(1);
     }
+}
 
-    DynamicImage::ImageRgba8(rgba)
+// sort a single channel as its own gray plane, keyed by its own value
+fn sort_channel(
+    rgba: &mut RgbaImage,
+    rgba_c: &RgbaImage,
+    idxs: &[(u32, u32)],
+    c: usize,
+    opts: &Opts,
+    rng: &mut StdRng,
+    debug: Option<&mut RgbaImage>,
+) {
+    let plane = idxs
+        .iter()
+        .map(|(x, y)| {
+            let p = rgba_c.get_pixel(*x, *y);
+
+            Rgba([p[c], p[c], p[c], p[3]])
+        })
+        .collect::<Vec<_>>();
+
+    let mut pixels = plane.iter().collect::<Vec<_>>();
+
+    let spans = spans(opts, &pixels, idxs, rng);
+
+    if let Some(debug) = debug {
+        draw_spans(debug, idxs, &spans, opts, Some(c), (1, 1));
+    }
+
+    sort_spans(&mut pixels[..], &spans, pixel_red);
+
+    for ((x, y), px) in idxs.iter().zip(pixels) {
+        rgba.get_pixel_mut(*x, *y)[c] = px[0];
+    }
+}
+
+// `idxs` are where the pixels are in the image, for the intervals that depend on position
+pub fn sort_pixels<P: Borrow<Rgba<u8>>>(
+    opts: &Opts,
+    pixels: &mut [P],
+    idxs: &[(u32, u32)],
+    rng: &mut StdRng,
+    sort_fn: impl Fn(&[u8]) -> u8,
+) {
+    let spans = spans(opts, pixels, idxs, rng);
+
+    sort_spans(pixels, &spans, sort_fn);
 }
 
-pub fn sort_pixels(opts: &Opts, pixels: &mut [&Rgba<u8>], sort_fn: impl Fn(&[u8]) -> u8) {
-    let mut reverse = opts.reverse;
+// the ranges of pixels the interval function picks, and whether to sort them in reverse
+pub fn spans<P: Borrow<Rgba<u8>>>(
+    opts: &Opts,
+    pixels: &[P],
+    idxs: &[(u32, u32)],
+    rng: &mut StdRng,
+) -> Vec<(Range<usize>, bool)> {
     let mut ctr = 0;
-    let interval_fn = match &opts.interval {
-        IntervalType::Random => interval_random,
-        IntervalType::Threshold => interval_threshold,
+    let mut spans = Vec::new();
+    let noise = match opts.interval {
+        IntervalType::Noise { seed, .. } => Some(noise::Perlin::new(seed)),
+        _ => None,
     };
 
-    let interval_fn_reverse = match &opts.interval {
-        IntervalType::Random => interval_none,
-        IntervalType::Threshold => interval_threshold_reverse,
+    let mut interval_fn = |ctr| match (&opts.interval, &noise) {
+        (IntervalType::Random, _) => interval_random(opts, pixels, rng, ctr),
+        (IntervalType::Threshold, _) => interval_threshold(opts, pixels, ctr),
+        (IntervalType::Color(range), _) => interval_color(opts, pixels, range, true, ctr),
+        (
+            IntervalType::Hysteresis {
+                key,
+                min,
+                max,
+                hold,
+            },
+            _,
+        ) => {
+            let window = (min.unwrap_or(opts.min), max.unwrap_or(opts.max));
+
+            interval_hysteresis(opts, pixels, *key, window, *hold, ctr)
+        }
+        (IntervalType::Noise { scale, .. }, Some(noise)) => {
+            interval_noise(opts, noise, *scale, idxs[ctr], pixels.len(), ctr)
+        }
+        (IntervalType::Noise { .. }, None) => unreachable!(),
+        (
+            IntervalType::Map {
+                map,
+                threshold,
+                delta,
+            },
+            _,
+        ) => interval_map(opts, map, *threshold, *delta, idxs, ctr),
+    };
+
+    let interval_fn_reverse = |ctr| match &opts.interval {
+        IntervalType::Threshold | IntervalType::Hysteresis { .. } => {
+            interval_threshold_reverse(opts, pixels, ctr)
+        }
+        IntervalType::Color(range) => interval_color(opts, pixels, range, false, ctr),
+        IntervalType::Map { map, threshold, .. } => {
+            interval_map_reverse(opts, map, *threshold, idxs, ctr)
+        }
+        _ => 0,
     };
 
     while ctr < pixels.len() {
-        let numel = interval_fn(opts, pixels, ctr).min(pixels.len() - ctr);
+        let reverse = opts.reverse != (opts.split && ctr >= pixels.len() / 2);
+        let numel = interval_fn(ctr).min(pixels.len() - ctr);
+
+        if numel > 0 {
+            spans.push((ctr..ctr + numel, reverse));
+        }
+
+        ctr += numel;
+        ctr += interval_fn_reverse(ctr);
+    }
+
+    constrain_spans(opts, spans, rng)
+}
+
+// merge spans separated by tiny gaps, drop the ones still too short, cut up the long ones
+// and keep each with `span_chance`
+fn constrain_spans(
+    opts: &Opts,
+    spans: Vec<(Range<usize>, bool)>,
+    rng: &mut StdRng,
+) -> Vec<(Range<usize>, bool)> {
+    if opts.min_span <= 1 && opts.max_span == usize::MAX && opts.span_chance >= 1.0 {
+        return spans;
+    }
+
+    let mut merged: Vec<(Range<usize>, bool)> = Vec::with_capacity(spans.len());
+
+    // short spans join a neighbour across a gap shorter than `min_span`, long ones stay apart
+    for (span, reverse) in spans {
+        match merged.last_mut() {
+            Some((last, r))
+                if *r == reverse
+                    && span.start - last.end < opts.min_span
+                    && (last.len() < opts.min_span || span.len() < opts.min_span) =>
+            {
+                last.end = span.end;
+            }
+            _ => merged.push((span, reverse)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .filter(|(span, _)| span.len() >= opts.min_span)
+        .flat_map(|(span, reverse)| {
+            let step = opts.max_span.max(1);
+
+            span.clone()
+                .step_by(step)
+                .map(move |start| (start..start.saturating_add(step).min(span.end), reverse))
+        })
+        .filter(|_| opts.span_chance >= 1.0 || rng.gen_bool(opts.span_chance.max(0.0)))
+        .collect()
+}
 
-        pixels[ctr..ctr + numel].sort_unstable_by(|l, r| {
-            if reverse {
-                sort_fn(&r.0).cmp(&sort_fn(&l.0))
+fn sort_spans<P: Borrow<Rgba<u8>>>(
+    pixels: &mut [P],
+    spans: &[(Range<usize>, bool)],
+    sort_fn: impl Fn(&[u8]) -> u8,
+) {
+    for (span, reverse) in spans {
+        pixels[span.clone()].sort_unstable_by(|l, r| {
+            let (l, r) = (&l.borrow().0, &r.borrow().0);
+
+            if *reverse {
+                sort_fn(r).cmp(&sort_fn(l))
             } else {
-                sort_fn(&l.0).cmp(&sort_fn(&r.0))
+                sort_fn(l).cmp(&sort_fn(r))
             }
         });
+    }
+}
 
-        ctr += numel;
-        ctr += interval_fn_reverse(opts, pixels, ctr);
+fn draw_spans(
+    debug: &mut RgbaImage,
+    idxs: &[(u32, u32)],
+    spans: &[(Range<usize>, bool)],
+    opts: &Opts,
+    channel: Option<usize>,
+    (bw, bh): (u32, u32),
+) {
+    const COLOURS: [[u8; 3]; 2] = [[255, 150, 0], [0, 170, 255]];
+
+    for (i, (span, _)) in spans.iter().enumerate() {
+        for j in span.clone() {
+            // fade in along the path to show which way it goes
+            let shade = if opts.debug_direction {
+                0.3 + 0.7 * (j + 1) as f32 / idxs.len() as f32
+            } else {
+                1.0
+            };
+
+            let (x, y) = idxs[j];
 
-        if opts.split && ctr >= pixels.len() / 2 {
-            reverse = !reverse;
+            for py in y * bh..(y + 1) * bh {
+                for px in x * bw..(x + 1) * bw {
+                    let p = debug.get_pixel_mut(px, py);
+
+                    match channel {
+                        Some(3) => {}
+                        Some(c) => p[c] = ([255.0, 150.0][i % 2] * shade) as u8,
+                        None => {
+                            for c in 0..3 {
+                                p[c] = (f32::from(COLOURS[i % 2][c]) * shade) as u8;
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
-fn circle_points(img: &RgbaImage, cx: u32, cy: u32, r: u32, angle: f64) -> Vec<(u32, u32)> {
-    let xr = 0..img.width();
-    let yr = 0..img.height();
-    let mut circle = Vec::new();
-    let mut point = |x: i32, y: i32| {
-        if xr.contains(&(x as u32)) && yr.contains(&(y as u32)) {
-            circle.push((x as u32, y as u32));
-        }
-    };
+// with `split` the spans stop at the middle, so each half can be sorted in its own direction
+fn split_limit(opts: &Opts, len: usize, ctr: usize) -> usize {
+    if opts.split && ctr < len / 2 {
+        len / 2 - ctr
+    } else {
+        len - ctr
+    }
+}
 
-    let mut circle_points = |cx: i32, cy: i32, x: i32, y: i32| {
-        if x == 0 {
-            point(cx, cy + y);
-            point(cx, cy - y);
-            point(cx + y, cy);
-            point(cx - y, cy);
-        } else if x == y {
-            point(cx + x, cy + y);
-            point(cx - x, cy + y);
-            point(cx + x, cy - y);
-            point(cx - x, cy - y);
-        } else if x < y {
-            point(cx + x, cy + y);
-            point(cx - x, cy + y);
-            point(cx + x, cy - y);
-            point(cx - x, cy - y);
-            point(cx + y, cy + x);
-            point(cx - y, cy + x);
-            point(cx + y, cy - x);
-            point(cx - y, cy - x);
-        }
-    };
+// span lengths follow noise over the image, so neighbouring paths get similar lengths
+fn interval_noise(
+    opts: &Opts,
+    noise: &noise::Perlin,
+    scale: f64,
+    (x, y): (u32, u32),
+    len: usize,
+    ctr: usize,
+) -> usize {
+    let n = noise.fbm(f64::from(x) / scale, f64::from(y) / scale, 2);
+    let t = (n * 0.75 + 0.5).clamp(0.0, 1.0);
+    let numel = opts.min + (t * opts.max.saturating_sub(opts.min) as f64).round() as usize;
+
+    1.max(numel.min(split_limit(opts, len, ctr)))
+}
 
-    let mut x = 0;
-    let mut y = r as i32;
-    let mut p = (5 - r as i32 * 4) / 4;
+fn interval_random<P: Borrow<Rgba<u8>>>(
+    opts: &Opts,
+    pixels: &[P],
+    rng: &mut StdRng,
+    ctr: usize,
+) -> usize {
+    if opts.split {
+        1.max(usize::min(
+            split_limit(opts, pixels.len(), ctr),
+            rng.gen_range(opts.min, opts.max),
+        ))
+    } else {
+        usize::min(pixels.len() - ctr, rng.gen_range(opts.min, opts.max))
+    }
+}
 
-    circle_points(cx as i32, cy as i32, x, y);
+// count the pixels inside (or outside) the colour range
+fn interval_color<P: Borrow<Rgba<u8>>>(
+    opts: &Opts,
+    pixels: &[P],
+    range: &ColorRange,
+    inside: bool,
+    ctr: usize,
+) -> usize {
+    let count = pixels[ctr..]
+        .iter()
+        .map(Borrow::borrow)
+        .take_while(|p| ((range.contains(p) != opts.invert) && mask_fn(opts, p)) == inside)
+        .count();
 
-    while x < y {
-        x += 1;
-        p += if p < 0 {
-            2 * x + 1
-        } else {
-            y -= 1;
-            2 * (x - y) + 1
-        };
+    if opts.split {
+        count.min(split_limit(opts, pixels.len(), ctr))
+    } else {
+        count
+    }
+}
 
-        circle_points(cx as i32, cy as i32, x, y);
+// a span goes on while `key` stays in its window, letting up to `hold` pixels in a row fall outside of it
+fn interval_hysteresis<P: Borrow<Rgba<u8>>>(
+    opts: &Opts,
+    pixels: &[P],
+    key: fn(&[u8]) -> u8,
+    (min, max): (usize, usize),
+    hold: usize,
+    ctr: usize,
+) -> usize {
+    let start = pixels[ctr].borrow();
+    let l = (opts.sort_fn)(&start.0) as usize;
+
+    if (l < opts.min || l > opts.max) != opts.invert || !mask_fn(opts, start) {
+        return 0;
     }
 
-    circle.sort_by(|a, b| {
-        let a = ((a.1 as f64 - cy as f64)
-            .atan2(a.0 as f64 - cx as f64)
-            .to_degrees()
-            - 270.0
-            - angle)
-            % 360.0;
+    let mut end = ctr + 1;
+    let mut misses = 0;
 
-        let b = ((b.1 as f64 - cy as f64)
-            .atan2(b.0 as f64 - cx as f64)
-            .to_degrees()
-            - 270.0
-            - angle)
-            % 360.0;
+    for (i, p) in pixels.iter().enumerate().skip(ctr + 1) {
+        let p = p.borrow();
+        let l = key(&p.0) as usize;
 
-        if a < b {
-            std::cmp::Ordering::Less
-        } else if a > b {
-            std::cmp::Ordering::Greater
+        if (l >= min && l <= max) != opts.invert && mask_fn(opts, p) {
+            end = i + 1;
+            misses = 0;
+        } else if misses < hold {
+            misses += 1;
         } else {
-            std::cmp::Ordering::Equal
+            break;
         }
-    });
+    }
 
-    circle.dedup();
-    circle
+    (end - ctr).min(split_limit(opts, pixels.len(), ctr))
 }
 
-fn interval_none(_: &Opts, _: &[&Rgba<u8>], _: usize) -> usize {
-    0
-}
+// spans follow the map instead of the pixels, breaking where it crosses the threshold or jumps by more than delta
+fn interval_map(
+    opts: &Opts,
+    map: &GrayImage,
+    threshold: Option<u8>,
+    delta: Option<u8>,
+    idxs: &[(u32, u32)],
+    ctr: usize,
+) -> usize {
+    let value = |i: usize| map.get_pixel(idxs[i].0, idxs[i].1).0[0];
+    let count = (ctr..idxs.len())
+        .take_while(|&i| {
+            let inside = threshold.is_none_or(|t| (value(i) >= t) != opts.invert);
+            let jump = i > ctr && delta.is_some_and(|d| value(i).abs_diff(value(i - 1)) > d);
+
+            inside && !jump
+        })
+        .count();
 
-fn interval_random(opts: &Opts, pixels: &[&Rgba<u8>], ctr: usize) -> usize {
-    use rand::{Rng, SeedableRng};
-    use std::sync::Mutex;
+    1.max(count.min(split_limit(opts, idxs.len(), ctr)))
+}
 
-    lazy_static::lazy_static! {
-        static ref RNG: Mutex<rand::rngs::StdRng> = Mutex::new(rand::rngs::StdRng::seed_from_u64(0));
-    }
+fn interval_map_reverse(
+    opts: &Opts,
+    map: &GrayImage,
+    threshold: Option<u8>,
+    idxs: &[(u32, u32)],
+    ctr: usize,
+) -> usize {
+    let count = (ctr..idxs.len())
+        .take_while(|&i| {
+            let value = map.get_pixel(idxs[i].0, idxs[i].1).0[0];
+
+            threshold.is_some_and(|t| (value >= t) == opts.invert)
+        })
+        .count();
 
     if opts.split {
-        1.max(usize::min(
-            pixels.len() / 2 - ctr,
-            RNG.lock().unwrap().gen_range(opts.min, opts.max),
-        ))
+        count.min(split_limit(opts, idxs.len(), ctr))
     } else {
-        usize::min(
-            pixels.len() - ctr,
-            RNG.lock().unwrap().gen_range(opts.min, opts.max),
-        )
+        count
     }
 }
 
-fn interval_threshold(opts: &Opts, pixels: &[&Rgba<u8>], ctr: usize) -> usize {
+fn interval_threshold<P: Borrow<Rgba<u8>>>(opts: &Opts, pixels: &[P], ctr: usize) -> usize {
     let count = pixels[ctr..]
         .iter()
+        .map(Borrow::borrow)
         .take_while(|p| {
             let l = (opts.sort_fn)(&p.0) as usize;
 
-            (l >= opts.min && l <= opts.max) != opts.invert && mask_fn(opts, *p)
+            (l >= opts.min && l <= opts.max) != opts.invert && mask_fn(opts, p)
         })
         .count();
 
     if opts.split {
-        1.max(count.min(pixels.len() / 2))
+        1.max(count.min(split_limit(opts, pixels.len(), ctr)))
     } else {
         count
     }
 }
 
-fn interval_threshold_reverse(opts: &Opts, pixels: &[&Rgba<u8>], ctr: usize) -> usize {
+fn interval_threshold_reverse<P: Borrow<Rgba<u8>>>(opts: &Opts, pixels: &[P], ctr: usize) -> usize {
     let count = pixels[ctr..]
         .iter()
+        .map(Borrow::borrow)
         .take_while(|p| {
             let l = (opts.sort_fn)(&p.0) as usize;
 
-            (l < opts.min || l > opts.max) != opts.invert || !mask_fn(opts, *p)
+            (l < opts.min || l > opts.max) != opts.invert || !mask_fn(opts, p)
         })
         .count();
 
     if opts.split {
-        1.max(count.min(pixels.len() / 2))
+        1.max(count.min(split_limit(opts, pixels.len(), ctr)))
     } else {
         count
     }
@@ -541,3 +1008,133 @@ fn interval_threshold_reverse(opts: &Opts, pixels: &[&Rgba<u8>], ctr: usize) ->
 fn mask_fn(opts: &Opts, p: &Rgba<u8>) -> bool {
     !(opts.mask_alpha && p[3] == 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(48, 32, |x, y| {
+            Rgba([(x * 5) as u8, (y * 7) as u8, (x * y) as u8, 255])
+        }))
+    }
+
+    #[test]
+    fn every_sort_type_is_a_permutation() {
+        let (cx, cy) = (Coord::Percent(50.0), Coord::Percent(50.0));
+        let sine = Harmonic {
+            shape: Waveform::Sine,
+            amp: 3.0,
+            lam: 10.0,
+            offset: 0.0,
+        };
+        let sort_types = vec![
+            SortType::Linear,
+            SortType::Diagonal { anti: false },
+            SortType::Diagonal { anti: true },
+            SortType::Zigzag,
+            SortType::Spiral,
+            SortType::Polygon {
+                cx,
+                cy,
+                sides: 5,
+                rotation: 0.0,
+                star: Some(0.5),
+                fit: true,
+            },
+            SortType::Circle { cx, cy },
+            SortType::Ellipse {
+                cx,
+                cy,
+                ratio: 0.5,
+                rotation: 30.0,
+            },
+            SortType::MultiCircle {
+                centres: vec![
+                    (Coord::Pixels(10), Coord::Pixels(10)),
+                    (Coord::Pixels(40), Coord::Pixels(20)),
+                ],
+            },
+            SortType::Swirl {
+                cx,
+                cy,
+                shape: SpiralShape::Archimedean { spacing: 4.0 },
+                arms: 3,
+                clockwise: false,
+            },
+            SortType::Swirl {
+                cx,
+                cy,
+                shape: SpiralShape::Logarithmic { growth: 0.2 },
+                arms: 1,
+                clockwise: true,
+            },
+            SortType::Radial {
+                cx,
+                cy,
+                rays: 64,
+                inward: true,
+            },
+            SortType::FlowField {
+                scale: 16.0,
+                octaves: 2,
+                seed: 1,
+                length: 20,
+            },
+            SortType::Contour {
+                along: Contour::Isophote,
+                smooth: 1.0,
+                length: 20,
+            },
+            SortType::Custom {
+                paths: vec![
+                    vec![(0.0, 0.0), (47.0, 31.0)],
+                    vec![(0.0, 31.0), (47.0, 0.0)],
+                ],
+                space: None,
+            },
+            SortType::Sine {
+                amp: 3.0,
+                lam: 10.0,
+                offset: 0.0,
+            },
+            SortType::Wave {
+                harmonics: vec![sine],
+                growth: 0.1,
+            },
+        ];
+
+        for sort_type in sort_types {
+            let opts = Opts {
+                sort_type,
+                angle: 20.0,
+                ..Opts::default()
+            };
+            let input = image();
+            let output = sort_image(&ProgressBar::hidden(), input.clone(), &opts, 0);
+
+            assert!(is_permutation(&input, &output));
+        }
+    }
+
+    #[test]
+    fn short_threshold_spans_bridge_small_gaps() {
+        let opts = Opts {
+            interval: IntervalType::Threshold,
+            min: 100,
+            min_span: 3,
+            ..Opts::default()
+        };
+        let pixels = [200, 10, 200, 10, 200, 10, 10, 10, 10, 200, 10, 200]
+            .iter()
+            .map(|&v| Rgba([v, v, v, 255]))
+            .collect::<Vec<_>>();
+        let idxs = (0..pixels.len() as u32).map(|i| (i, 0)).collect::<Vec<_>>();
+        let rng = &mut StdRng::seed_from_u64(0);
+
+        assert_eq!(
+            spans(&opts, &pixels, &idxs, rng),
+            vec![(0..5, false), (9..12, false)]
+        );
+    }
+}
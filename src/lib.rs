@@ -1,6 +1,14 @@
+pub mod analyze;
+pub mod blend;
+pub mod color;
+pub mod custom;
 pub mod img;
+pub mod noise;
+pub mod path;
+pub mod segment;
 // pub mod vid;
 
+#[derive(Clone)]
 pub struct Opts {
     pub sort_type: SortType,
     pub sort_fn: fn(&[u8]) -> u8,
@@ -11,22 +19,185 @@ pub struct Opts {
     pub split: bool,
     pub min: usize,
     pub max: usize,
+    pub auto_min: Option<Threshold>,
+    pub auto_max: Option<Threshold>,
+    pub min_span: usize,
+    pub max_span: usize,
+    pub span_chance: f64,
     pub angle: f64,
     pub vertical: bool,
     pub resize: Option<Scale>,
     pub internal_scale: Option<Scale>,
+    pub segments: Option<Segments>,
+    pub regions: Vec<Region>,
+    pub blend: Option<Blend>,
+    pub channels: Vec<usize>,
+    pub component: Option<Component>,
+    pub block: Option<Block>,
+    pub debug_intervals: Option<std::path::PathBuf>,
+    pub debug_direction: bool,
 }
 
+#[derive(Clone)]
 pub enum SortType {
     Linear,
+    Diagonal {
+        anti: bool,
+    },
+    Zigzag,
     Spiral,
-    Circle { cx: u32, cy: u32 },
-    Sine { amp: f64, lam: f64, offset: f64 },
+    Polygon {
+        cx: Coord,
+        cy: Coord,
+        sides: u32,
+        rotation: f64,
+        star: Option<f64>,
+        fit: bool,
+    },
+    Circle {
+        cx: Coord,
+        cy: Coord,
+    },
+    Ellipse {
+        cx: Coord,
+        cy: Coord,
+        ratio: f64,
+        rotation: f64,
+    },
+    MultiCircle {
+        centres: Vec<(Coord, Coord)>,
+    },
+    Swirl {
+        cx: Coord,
+        cy: Coord,
+        shape: SpiralShape,
+        arms: u32,
+        clockwise: bool,
+    },
+    Radial {
+        cx: Coord,
+        cy: Coord,
+        rays: u32,
+        inward: bool,
+    },
+    FlowField {
+        scale: f64,
+        octaves: u32,
+        seed: u64,
+        length: usize,
+    },
+    Contour {
+        along: Contour,
+        smooth: f32,
+        length: usize,
+    },
+    Custom {
+        paths: Vec<Vec<(f64, f64)>>,
+        space: Option<custom::Space>,
+    },
+    Sine {
+        amp: f64,
+        lam: f64,
+        offset: f64,
+    },
+    Wave {
+        harmonics: Vec<Harmonic>,
+        growth: f64,
+    },
 }
 
+#[derive(Clone)]
 pub enum IntervalType {
     Random,
     Threshold,
+    // start where the sort function is within `min..=max`, and carry on while `key` is within this window,
+    // which defaults to the resolved `min..=max`
+    Hysteresis {
+        key: fn(&[u8]) -> u8,
+        min: Option<usize>,
+        max: Option<usize>,
+        hold: usize,
+    },
+    Color(ColorRange),
+    Noise {
+        scale: f64,
+        seed: u64,
+    },
+    Map {
+        map: image::GrayImage,
+        threshold: Option<u8>,
+        delta: Option<u8>,
+    },
+}
+
+// hue in degrees, wrapping around when the start is past the end, saturation and lightness in percent
+#[derive(Clone, Copy)]
+pub struct ColorRange {
+    pub hue: Option<(f32, f32)>,
+    pub saturation: (f32, f32),
+    pub lightness: (f32, f32),
+}
+
+// pick `min` or `max` from the histogram of the sort function over the image
+#[derive(Clone, Copy)]
+pub enum Threshold {
+    Otsu,
+    Percentile(f32),
+}
+
+#[derive(Clone)]
+pub enum Segments {
+    Labels(image::RgbaImage),
+    KMeans { k: u32, seed: u64 },
+    Slic { size: u32, compactness: f32 },
+}
+
+// sort the region with this label using other options
+#[derive(Clone)]
+pub struct Region {
+    pub label: u32,
+    pub opts: Opts,
+}
+
+#[derive(Clone, Copy)]
+pub struct Block {
+    pub width: u32,
+    pub height: u32,
+    pub key: BlockKey,
+}
+
+#[derive(Clone, Copy)]
+pub enum BlockKey {
+    Mean,
+    Median,
+}
+
+#[derive(Clone, Copy)]
+pub enum Component {
+    Lightness,
+    Hue,
+    Chroma,
+    Alpha,
+}
+
+// mix the sorted image back into the original
+#[derive(Clone)]
+pub struct Blend {
+    pub mode: BlendMode,
+    pub opacity: f32,
+    pub mask: Option<image::GrayImage>,
+    pub feather: f32,
+}
+
+#[derive(Clone, Copy)]
+pub enum BlendMode {
+    Normal,
+    Lighten,
+    Darken,
+    Difference,
+    Screen,
+    Multiply,
+    Overlay,
 }
 
 #[derive(Clone, Copy)]
@@ -35,6 +206,41 @@ pub enum Scale {
     Multiply(f32),
 }
 
+#[derive(Clone, Copy)]
+pub enum SpiralShape {
+    Archimedean { spacing: f64 },
+    Logarithmic { growth: f64 },
+}
+
+#[derive(Clone, Copy)]
+pub struct Harmonic {
+    pub shape: Waveform,
+    pub amp: f64,
+    pub lam: f64,
+    pub offset: f64,
+}
+
+#[derive(Clone, Copy)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+    Noise,
+}
+
+#[derive(Clone, Copy)]
+pub enum Contour {
+    Isophote,
+    Gradient,
+}
+
+#[derive(Clone, Copy)]
+pub enum Coord {
+    Pixels(u32),
+    Percent(f32),
+}
+
 impl Default for Opts {
     fn default() -> Self {
         Opts {
@@ -47,10 +253,23 @@ impl Default for Opts {
             split: false,
             min: 0,
             max: 255,
+            auto_min: None,
+            auto_max: None,
+            min_span: 0,
+            max_span: usize::MAX,
+            span_chance: 1.0,
             angle: 0.0,
             vertical: false,
             resize: None,
             internal_scale: None,
+            segments: None,
+            regions: Vec::new(),
+            blend: None,
+            channels: Vec::new(),
+            component: None,
+            block: None,
+            debug_intervals: None,
+            debug_direction: false,
         }
     }
 }
@@ -64,6 +283,34 @@ impl Scale {
     }
 }
 
+impl ColorRange {
+    pub fn contains(&self, p: &image::Rgba<u8>) -> bool {
+        let (h, s, l) = color::to_hsl(p);
+        let (s, l) = (s * 100.0, l * 100.0);
+        let hue = match self.hue {
+            // grays have no hue
+            Some(_) if s == 0.0 => false,
+            Some((start, end)) if start <= end => h >= start && h <= end,
+            Some((start, end)) => h >= start || h <= end,
+            None => true,
+        };
+
+        hue && s >= self.saturation.0
+            && s <= self.saturation.1
+            && l >= self.lightness.0
+            && l <= self.lightness.1
+    }
+}
+
+impl Coord {
+    pub fn calc(self, size: u32) -> u32 {
+        match self {
+            Coord::Pixels(p) => p,
+            Coord::Percent(p) => (size as f32 * p / 100.0) as u32,
+        }
+    }
+}
+
 pub fn pixel_red(p: &[u8]) -> u8 {
     p[0]
 }
@@ -96,9 +343,9 @@ pub fn pixel_hue(p: &[u8]) -> u8 {
     }
 
     match p[..3].iter().enumerate().max_by_key(|&(_, e)| e) {
-        Some((0, _)) => (i16::from(p[1]) - i16::from(p[2])).abs() as u8 / c * 43,
-        Some((1, _)) => (i16::from(p[2]) - i16::from(p[0])).abs() as u8 / c * 43 + 85,
-        Some((2, _)) => (i16::from(p[0]) - i16::from(p[1])).abs() as u8 / c * 43 + 171,
+        Some((0, _)) => (i16::from(p[1]) - i16::from(p[2])).unsigned_abs() as u8 / c * 43,
+        Some((1, _)) => (i16::from(p[2]) - i16::from(p[0])).unsigned_abs() as u8 / c * 43 + 85,
+        Some((2, _)) => (i16::from(p[0]) - i16::from(p[1])).unsigned_abs() as u8 / c * 43 + 171,
         _ => 0,
     }
 }
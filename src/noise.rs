@@ -0,0 +1,68 @@
+use rand::{seq::SliceRandom, SeedableRng};
+
+pub struct Perlin {
+    perm: Vec<u8>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut perm = (0..=255).collect::<Vec<u8>>();
+
+        perm.shuffle(&mut rand::rngs::StdRng::seed_from_u64(seed));
+        perm.extend_from_within(..);
+
+        Perlin { perm }
+    }
+
+    pub fn get(&self, x: f64, y: f64) -> f64 {
+        let (xf, yf) = (x.floor(), y.floor());
+        let (xi, yi) = ((xf as i64 & 255) as usize, (yf as i64 & 255) as usize);
+        let (x, y) = (x - xf, y - yf);
+        let (u, v) = (fade(x), fade(y));
+        let hash = |i: usize, j: usize| self.perm[self.perm[xi + i] as usize + yi + j];
+
+        lerp(
+            v,
+            lerp(u, grad(hash(0, 0), x, y), grad(hash(1, 0), x - 1.0, y)),
+            lerp(
+                u,
+                grad(hash(0, 1), x, y - 1.0),
+                grad(hash(1, 1), x - 1.0, y - 1.0),
+            ),
+        )
+    }
+
+    pub fn fbm(&self, x: f64, y: f64, octaves: u32) -> f64 {
+        let (mut sum, mut total, mut amp, mut freq) = (0.0, 0.0, 1.0, 1.0);
+
+        for _ in 0..octaves.max(1) {
+            sum += self.get(x * freq, y * freq) * amp;
+            total += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+
+        sum / total
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => x - y,
+        2 => -x + y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
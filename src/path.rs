@@ -0,0 +1,828 @@
+use super::custom::Space;
+use super::noise::Perlin;
+use super::{pixel_luma, Contour, Harmonic, SpiralShape, Waveform};
+use image::{ImageBuffer, Luma, RgbaImage};
+
+pub fn linear(width: u32, height: u32, angle: f64) -> Vec<Vec<(u32, u32)>> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let offset = |x: f64, y: f64| y * cos - x * sin;
+    let along = |x: f64, y: f64| x * cos + y * sin;
+    let (w, h) = (f64::from(width), f64::from(height));
+    let corners = [
+        offset(0.0, 0.0),
+        offset(w, 0.0),
+        offset(0.0, h),
+        offset(w, h),
+    ];
+    let min = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut lines = vec![Vec::new(); (max - min).ceil() as usize + 1];
+
+    for y in 0..height {
+        for x in 0..width {
+            let (cx, cy) = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+            let line = (offset(cx, cy) - min).floor() as usize;
+
+            lines[line].push((along(cx, cy), x, y));
+        }
+    }
+
+    lines
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .map(|mut line| {
+            line.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            line.into_iter().map(|(_, x, y)| (x, y)).collect()
+        })
+        .collect()
+}
+
+pub fn diagonal(width: u32, height: u32, anti: bool) -> Vec<Vec<(u32, u32)>> {
+    (0..width + height - 1)
+        .map(|k| {
+            (0..width)
+                .filter_map(|x| {
+                    let y = if anti {
+                        k.checked_sub(x)?
+                    } else {
+                        (x + height - 1).checked_sub(k)?
+                    };
+
+                    Some((x, y)).filter(|_| y < height)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn zigzag(width: u32, height: u32, vertical: bool) -> Vec<Vec<(u32, u32)>> {
+    let (outer, inner) = if vertical {
+        (width, height)
+    } else {
+        (height, width)
+    };
+
+    let snake = (0..outer)
+        .flat_map(|i| {
+            (0..inner).map(move |j| {
+                let j = if i % 2 == 0 { j } else { inner - j - 1 };
+
+                if vertical {
+                    (i, j)
+                } else {
+                    (j, i)
+                }
+            })
+        })
+        .collect();
+
+    vec![snake]
+}
+
+pub fn spiral(width: u32, height: u32, angle: f64) -> Vec<Vec<(u32, u32)>> {
+    let (w, h) = (f64::from(width), f64::from(height));
+    let corner = (-h).atan2(-w).to_degrees() + 90.0;
+
+    polygon(
+        width,
+        height,
+        (w / 2.0, h / 2.0),
+        4,
+        0.0,
+        None,
+        true,
+        angle + corner,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn polygon(
+    width: u32,
+    height: u32,
+    (cx, cy): (f64, f64),
+    sides: u32,
+    rotation: f64,
+    star: Option<f64>,
+    fit: bool,
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    use std::f64::consts::{PI, TAU};
+
+    let sides = sides.max(3);
+    let rotation = rotation.to_radians() + PI / 2.0;
+    let corners = [
+        (0.0, 0.0),
+        (f64::from(width), 0.0),
+        (0.0, f64::from(height)),
+        (f64::from(width), f64::from(height)),
+    ];
+
+    // how far inside the outermost ring a pixel is, rings are a pixel thick where they are thinnest
+    let inset: Box<dyn Fn(f64, f64) -> f64> = match star {
+        None => {
+            let normals = (0..sides)
+                .map(|k| (rotation + TAU * f64::from(k) / f64::from(sides)).sin_cos())
+                .map(|(sin, cos)| (cos, sin))
+                .collect::<Vec<_>>();
+
+            let support = |&(nx, ny): &(f64, f64)| {
+                corners
+                    .iter()
+                    .map(|(x, y)| (x - cx) * nx + (y - cy) * ny)
+                    .fold(f64::NEG_INFINITY, f64::max)
+            };
+
+            let mut offsets = normals.iter().map(support).collect::<Vec<_>>();
+
+            if !fit {
+                let max = offsets.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+                offsets.iter_mut().for_each(|o| *o = max);
+            }
+
+            Box::new(move |dx, dy| {
+                normals
+                    .iter()
+                    .zip(&offsets)
+                    .map(|((nx, ny), o)| o - dx * nx - dy * ny)
+                    .fold(f64::INFINITY, f64::min)
+            })
+        }
+        Some(inner) => {
+            let sector = PI / f64::from(sides);
+            let inner = inner.clamp(f64::EPSILON, 1.0);
+
+            // the distance from the centre to the edge of a star with its points at distance 1
+            let edge = move |theta: f64| {
+                let t = (theta - rotation + PI).rem_euclid(2.0 * sector);
+                let ((ra, ta), (rb, tb)) = if t < sector {
+                    ((1.0, 0.0), (inner, sector))
+                } else {
+                    ((inner, sector), (1.0, 2.0 * sector))
+                };
+
+                let (ax, ay) = (ra * f64::cos(ta), ra * f64::sin(ta));
+                let (bx, by) = (rb * f64::cos(tb), rb * f64::sin(tb));
+                let (uy, ux) = t.sin_cos();
+
+                (ax * by - ay * bx) / (ux * (by - ay) - uy * (bx - ax))
+            };
+
+            let gauge = move |dx: f64, dy: f64| dx.hypot(dy) * inner / edge(dy.atan2(dx));
+            let max = corners
+                .iter()
+                .map(|(x, y)| gauge(x - cx, y - cy))
+                .fold(0.0, f64::max);
+
+            Box::new(move |dx, dy| max - gauge(dx, dy))
+        }
+    };
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+            let bearing = (dy.atan2(dx).to_degrees() + 90.0 - angle).rem_euclid(360.0);
+
+            pixels.push((inset(dx, dy).max(0.0) as u32, bearing, (x, y)));
+        }
+    }
+
+    group(pixels)
+}
+
+pub fn circle(width: u32, height: u32, cx: u32, cy: u32, angle: f64) -> Vec<Vec<(u32, u32)>> {
+    let dist =
+        |x: u32, y: u32| ((x as f64 - cx as f64).powi(2) + (y as f64 - cy as f64).powi(2)).sqrt();
+    let n = dist(0, 0)
+        .max(dist(0, height))
+        .max(dist(width, 0))
+        .max(dist(width, height))
+        .ceil() as u32;
+
+    (0..n)
+        .map(|r| circle_points(width, height, cx, cy, r, angle))
+        .collect()
+}
+
+pub fn ellipse(
+    width: u32,
+    height: u32,
+    (cx, cy): (u32, u32),
+    ratio: f64,
+    rotation: f64,
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    // measure along the shorter radius, so no ring is thinner than a pixel
+    let scale = ratio.min(1.0);
+
+    rings(width, height, &[(cx, cy)], angle, |dx, dy| {
+        let (u, v) = (dx * cos + dy * sin, dy * cos - dx * sin);
+
+        u.hypot(v / ratio) * scale
+    })
+}
+
+pub fn multi_circle(
+    width: u32,
+    height: u32,
+    centres: &[(u32, u32)],
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    rings(width, height, centres, angle, f64::hypot)
+}
+
+fn rings(
+    width: u32,
+    height: u32,
+    centres: &[(u32, u32)],
+    angle: f64,
+    dist: impl Fn(f64, f64) -> f64,
+) -> Vec<Vec<(u32, u32)>> {
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let nearest = centres
+                .iter()
+                .enumerate()
+                .map(|(c, &(cx, cy))| {
+                    let (dx, dy) = (x as f64 - cx as f64, y as f64 - cy as f64);
+
+                    (c, dist(dx, dy), dx, dy)
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            if let Some((c, d, dx, dy)) = nearest {
+                let bearing = (dy.atan2(dx).to_degrees() + 90.0 - angle).rem_euclid(360.0);
+
+                pixels.push(((c, d.floor() as u32), bearing, (x, y)));
+            }
+        }
+    }
+
+    group(pixels)
+}
+
+pub fn swirl(
+    width: u32,
+    height: u32,
+    (cx, cy): (u32, u32),
+    shape: SpiralShape,
+    arms: u32,
+    clockwise: bool,
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    use std::f64::consts::TAU;
+
+    let arms = arms.max(1);
+    let corner = |x: u32, y: u32| (x as f64 - cx as f64).hypot(y as f64 - cy as f64);
+    let rmax = corner(0, 0)
+        .max(corner(0, height))
+        .max(corner(width, 0))
+        .max(corner(width, height));
+
+    let ln = |growth: f64| growth.max(1.0 + f64::EPSILON).ln() * f64::from(arms);
+
+    // one turn of the spiral is split into `pitch` lanes that are about a pixel wide,
+    // and every run of `pitch / arms` neighbouring lanes makes up one arm
+    let pitch = match shape {
+        SpiralShape::Archimedean { spacing } => (spacing * f64::from(arms)).round(),
+        SpiralShape::Logarithmic { growth } => (rmax * ln(growth)).ceil(),
+    }
+    .max(f64::from(arms));
+
+    let turns = |r: f64| match shape {
+        SpiralShape::Archimedean { .. } => r / pitch,
+        SpiralShape::Logarithmic { growth } => r.max(0.5).ln() / ln(growth),
+    };
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f64 - cx as f64, y as f64 - cy as f64);
+            let theta = dy.atan2(dx) - angle.to_radians();
+            let t = if clockwise { theta } else { -theta }.rem_euclid(TAU) / TAU;
+            let lane = ((turns(dx.hypot(dy)) - t) * pitch).floor() as i64;
+            let pitch = pitch as i64;
+
+            // each arm is sorted as a whole, from the centre outwards
+            pixels.push((
+                lane.rem_euclid(pitch) * i64::from(arms) / pitch,
+                lane.div_euclid(pitch) as f64 + t,
+                (x, y),
+            ));
+        }
+    }
+
+    group(pixels)
+}
+
+pub fn radial(
+    width: u32,
+    height: u32,
+    (cx, cy): (u32, u32),
+    rays: u32,
+    inward: bool,
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    let corner = |x: u32, y: u32| (x as f64 - cx as f64).hypot(y as f64 - cy as f64);
+    let rays = if rays == 0 {
+        let rmax = corner(0, 0)
+            .max(corner(0, height))
+            .max(corner(width, 0))
+            .max(corner(width, height));
+
+        (rmax * std::f64::consts::TAU).ceil() as u32
+    } else {
+        rays
+    };
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f64 - cx as f64, y as f64 - cy as f64);
+            let bearing = (dy.atan2(dx).to_degrees() + 90.0 - angle).rem_euclid(360.0);
+            let ray = ((bearing / 360.0 * f64::from(rays)) as u32).min(rays - 1);
+            let r = dx.hypot(dy);
+
+            pixels.push((ray, if inward { -r } else { r }, (x, y)));
+        }
+    }
+
+    group(pixels)
+}
+
+pub fn streamlines(
+    width: u32,
+    height: u32,
+    length: usize,
+    direction: impl Fn(f64, f64) -> f64,
+) -> Vec<Vec<(u32, u32)>> {
+    let length = if length == 0 {
+        (width * height) as usize
+    } else {
+        length
+    };
+
+    let mut visited = vec![false; (width * height) as usize];
+    let mut runs = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if visited[(y * width + x) as usize] {
+                continue;
+            }
+
+            visited[(y * width + x) as usize] = true;
+
+            // trace against the flow first, then reverse that half so the run follows the flow
+            let mut run = Vec::new();
+
+            for &sign in &[-1.0, 1.0] {
+                let (mut px, mut py) = (x as f64 + 0.5, y as f64 + 0.5);
+                let mut last = (x, y);
+
+                if sign > 0.0 {
+                    run.reverse();
+                    run.push((x, y));
+                }
+
+                // a step can stay inside the same pixel, so allow a few more steps than pixels
+                for _ in 0..length * 2 {
+                    if run.len() >= length {
+                        break;
+                    }
+
+                    let (sin, cos) = direction(px, py).sin_cos();
+
+                    px += cos * sign;
+                    py += sin * sign;
+
+                    if px < 0.0 || py < 0.0 || px >= width as f64 || py >= height as f64 {
+                        break;
+                    }
+
+                    let (nx, ny) = (px as u32, py as u32);
+
+                    if last == (nx, ny) {
+                        continue;
+                    }
+
+                    if visited[(ny * width + nx) as usize] {
+                        break;
+                    }
+
+                    visited[(ny * width + nx) as usize] = true;
+                    last = (nx, ny);
+                    run.push(last);
+                }
+            }
+
+            runs.push(run);
+        }
+    }
+
+    runs
+}
+
+pub fn contour(
+    image: &RgbaImage,
+    along: Contour,
+    smooth: f32,
+    length: usize,
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    let (width, height) = image.dimensions();
+    let luma = ImageBuffer::from_fn(width, height, |x, y| {
+        Luma([f32::from(pixel_luma(&image.get_pixel(x, y).0))])
+    });
+
+    let luma = if smooth > 0.0 {
+        image::imageops::blur(&luma, smooth)
+    } else {
+        luma
+    };
+
+    let at = |x: i64, y: i64| {
+        let x = x.clamp(0, i64::from(width) - 1) as u32;
+        let y = y.clamp(0, i64::from(height) - 1) as u32;
+
+        f64::from(luma.get_pixel(x, y).0[0])
+    };
+
+    let offset = match along {
+        Contour::Isophote => std::f64::consts::FRAC_PI_2,
+        Contour::Gradient => 0.0,
+    };
+
+    let angle = angle.to_radians();
+
+    streamlines(width, height, length, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+            - at(x - 1, y - 1)
+            - 2.0 * at(x - 1, y)
+            - at(x - 1, y + 1);
+        let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+            - at(x - 1, y - 1)
+            - 2.0 * at(x, y - 1)
+            - at(x + 1, y - 1);
+
+        // flat areas have no direction of their own, so they fall back to the sort angle
+        if gx.hypot(gy) < 1.0 {
+            angle
+        } else {
+            gy.atan2(gx) + offset
+        }
+    })
+}
+
+pub fn polylines(
+    width: u32,
+    height: u32,
+    paths: &[Vec<(f64, f64)>],
+    space: Option<Space>,
+) -> Vec<Vec<(u32, u32)>> {
+    let (ox, oy, sx, sy) = match space {
+        Some((x, y, w, h)) => (x, y, f64::from(width) / w, f64::from(height) / h),
+        None => (0.0, 0.0, 1.0, 1.0),
+    };
+
+    let (w, h) = (i64::from(width), i64::from(height));
+    let pixel = |(x, y): (f64, f64)| {
+        (
+            (x.floor() as i64).clamp(0, w - 1),
+            (y.floor() as i64).clamp(0, h - 1),
+        )
+    };
+
+    let mut runs = paths
+        .iter()
+        .map(|path| {
+            let points = path
+                .iter()
+                .map(|(x, y)| ((x - ox) * sx, (y - oy) * sy))
+                .collect::<Vec<_>>();
+
+            let mut run = Vec::new();
+
+            for (i, &a) in points.iter().enumerate() {
+                let b = points.get(i + 1).cloned().unwrap_or(a);
+
+                // only step along the part inside the image, however far off it the path goes
+                let ((x0, y0), (x1, y1)) = match clip(a, b, f64::from(width), f64::from(height)) {
+                    Some((a, b)) => (pixel(a), pixel(b)),
+                    None => continue,
+                };
+
+                let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+                let (stepx, stepy) = ((x1 - x0).signum(), (y1 - y0).signum());
+                let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+
+                loop {
+                    if run.last() != Some(&(x as u32, y as u32)) {
+                        run.push((x as u32, y as u32));
+                    }
+
+                    if (x, y) == (x1, y1) {
+                        break;
+                    }
+
+                    let e2 = 2 * err;
+
+                    if e2 >= dy {
+                        err += dy;
+                        x += stepx;
+                    }
+
+                    if e2 <= dx {
+                        err += dx;
+                        y += stepy;
+                    }
+                }
+            }
+
+            run
+        })
+        .collect::<Vec<_>>();
+
+    // pixels that no path passes through stay where they are
+    let mut on_path = vec![false; (width * height) as usize];
+
+    for &(x, y) in runs.iter().flatten() {
+        on_path[(y * width + x) as usize] = true;
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            if !on_path[(y * width + x) as usize] {
+                runs.push(vec![(x, y)]);
+            }
+        }
+    }
+
+    runs
+}
+
+// the part of the segment from `a` to `b` inside `[0, width] x [0, height]`, by liang-barsky
+fn clip(a: (f64, f64), b: (f64, f64), width: f64, height: f64) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+
+    for &(p, q) in &[
+        (-dx, a.0),
+        (dx, width - a.0),
+        (-dy, a.1),
+        (dy, height - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+
+    let (a, b) = (
+        (a.0 + t0 * dx, a.1 + t0 * dy),
+        (a.0 + t1 * dx, a.1 + t1 * dy),
+    );
+
+    if t0 <= t1 && [a.0, a.1, b.0, b.1].iter().all(|v| v.is_finite()) {
+        Some((a, b))
+    } else {
+        None
+    }
+}
+
+fn group<K: Ord + Copy>(mut pixels: Vec<(K, f64, (u32, u32))>) -> Vec<Vec<(u32, u32)>> {
+    pixels.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.partial_cmp(&b.1).unwrap()));
+
+    let mut runs: Vec<Vec<(u32, u32)>> = Vec::new();
+    let mut run = None;
+
+    for (key, _, px) in pixels {
+        if run != Some(key) {
+            run = Some(key);
+            runs.push(Vec::new());
+        }
+
+        runs.last_mut().unwrap().push(px);
+    }
+
+    runs
+}
+
+pub fn wave(
+    width: u32,
+    height: u32,
+    harmonics: &[Harmonic],
+    growth: f64,
+    angle: f64,
+) -> Vec<Vec<(u32, u32)>> {
+    use std::f64::consts::{PI, TAU};
+
+    let noise = Perlin::new(0);
+    let (c_x, c_y) = (f64::from(width) / 2.0, f64::from(height) / 2.0);
+    let diag = f64::from(width).hypot(f64::from(height));
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f64 + 0.5 - c_x, y as f64 + 0.5 - c_y);
+            let (u, v) = (
+                dx * cos + dy * sin + diag / 2.0,
+                dy * cos - dx * sin + diag / 2.0,
+            );
+            let amp = 1.0 + growth * v / diag;
+            let offset = harmonics
+                .iter()
+                .map(|h| {
+                    let p = u / h.lam + h.offset;
+                    let value = match h.shape {
+                        Waveform::Sine => p.sin(),
+                        Waveform::Square => p.sin().signum(),
+                        Waveform::Triangle => p.sin().asin() * 2.0 / PI,
+                        Waveform::Sawtooth => p.rem_euclid(TAU) / PI - 1.0,
+                        Waveform::Noise => (noise.get(p / TAU, 0.5) * 1.5).clamp(-1.0, 1.0),
+                    };
+
+                    value * h.amp * amp
+                })
+                .sum::<f64>();
+
+            pixels.push(((v - offset).floor() as i64, u, (x, y)));
+        }
+    }
+
+    group(pixels)
+}
+
+pub fn cover(width: u32, height: u32, runs: Vec<Vec<(u32, u32)>>) -> Vec<Vec<(u32, u32)>> {
+    let idx = |x: u32, y: u32| (y * width + x) as usize;
+    let mut owner = vec![None; (width * height) as usize];
+    let mut queue = std::collections::VecDeque::new();
+    let mut runs = runs
+        .into_iter()
+        .enumerate()
+        .map(|(r, run)| {
+            let mut kept = Vec::with_capacity(run.len());
+
+            for (x, y) in run {
+                if x < width && y < height && owner[idx(x, y)].is_none() {
+                    owner[idx(x, y)] = Some((r, kept.len()));
+                    queue.push_back((x, y));
+                    kept.push((x, y));
+                }
+            }
+
+            kept
+        })
+        .collect::<Vec<_>>();
+
+    let mut leftovers = Vec::new();
+
+    while let Some((x, y)) = queue.pop_front() {
+        let root = owner[idx(x, y)];
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+
+        for (nx, ny) in neighbours.iter().cloned() {
+            if nx < width && ny < height && owner[idx(nx, ny)].is_none() {
+                owner[idx(nx, ny)] = root;
+                queue.push_back((nx, ny));
+                leftovers.push((root.unwrap(), leftovers.len(), (nx, ny)));
+            }
+        }
+    }
+
+    if !leftovers.is_empty() {
+        leftovers.sort_unstable();
+
+        let mut leftovers = leftovers.into_iter().peekable();
+
+        for (r, run) in runs.iter_mut().enumerate() {
+            if leftovers.peek().is_none_or(|&((lr, _), _, _)| lr != r) {
+                continue;
+            }
+
+            let mut merged = Vec::with_capacity(run.len());
+
+            for (pos, px) in run.drain(..).enumerate() {
+                merged.push(px);
+
+                while let Some(&(_, _, px)) =
+                    leftovers.peek().filter(|&&(root, _, _)| root == (r, pos))
+                {
+                    merged.push(px);
+                    leftovers.next();
+                }
+            }
+
+            *run = merged;
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            if owner[idx(x, y)].is_none() {
+                runs.push(vec![(x, y)]);
+            }
+        }
+    }
+
+    runs.retain(|run| !run.is_empty());
+    runs
+}
+
+fn circle_points(width: u32, height: u32, cx: u32, cy: u32, r: u32, angle: f64) -> Vec<(u32, u32)> {
+    let xr = 0..width;
+    let yr = 0..height;
+    let mut circle = Vec::new();
+    let mut point = |x: i32, y: i32| {
+        if xr.contains(&(x as u32)) && yr.contains(&(y as u32)) {
+            circle.push((x as u32, y as u32));
+        }
+    };
+
+    let mut circle_points = |cx: i32, cy: i32, x: i32, y: i32| {
+        if x == 0 {
+            point(cx, cy + y);
+            point(cx, cy - y);
+            point(cx + y, cy);
+            point(cx - y, cy);
+        } else if x == y {
+            point(cx + x, cy + y);
+            point(cx - x, cy + y);
+            point(cx + x, cy - y);
+            point(cx - x, cy - y);
+        } else if x < y {
+            point(cx + x, cy + y);
+            point(cx - x, cy + y);
+            point(cx + x, cy - y);
+            point(cx - x, cy - y);
+            point(cx + y, cy + x);
+            point(cx - y, cy + x);
+            point(cx + y, cy - x);
+            point(cx - y, cy - x);
+        }
+    };
+
+    let mut x = 0;
+    let mut y = r as i32;
+    let mut p = (5 - r as i32 * 4) / 4;
+
+    circle_points(cx as i32, cy as i32, x, y);
+
+    while x < y {
+        x += 1;
+        p += if p < 0 {
+            2 * x + 1
+        } else {
+            y -= 1;
+            2 * (x - y) + 1
+        };
+
+        circle_points(cx as i32, cy as i32, x, y);
+    }
+
+    circle.sort_by(|a, b| {
+        let a = ((a.1 as f64 - cy as f64)
+            .atan2(a.0 as f64 - cx as f64)
+            .to_degrees()
+            - 270.0
+            - angle)
+            % 360.0;
+
+        let b = ((b.1 as f64 - cy as f64)
+            .atan2(b.0 as f64 - cx as f64)
+            .to_degrees()
+            - 270.0
+            - angle)
+            % 360.0;
+
+        if a < b {
+            std::cmp::Ordering::Less
+        } else if a > b {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    });
+
+    circle.dedup();
+    circle
+}
@@ -0,0 +1,262 @@
+use super::Segments;
+use image::{imageops, RgbaImage};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::VecDeque;
+
+const ITERATIONS: usize = 10;
+
+pub fn labels(image: &RgbaImage, segments: &Segments) -> Vec<u32> {
+    match segments {
+        Segments::Labels(map) => label_map(image, map),
+        Segments::KMeans { k, seed } => kmeans(image, *k, *seed),
+        Segments::Slic { size, compactness } => slic(image, *size, *compactness),
+    }
+}
+
+// cut every run wherever it crosses from one region into another
+pub fn split(runs: Vec<Vec<(u32, u32)>>, labels: &[u32], width: u32) -> Vec<Vec<(u32, u32)>> {
+    let label = |(x, y): (u32, u32)| labels[(y * width + x) as usize];
+    let mut res = Vec::with_capacity(runs.len());
+
+    for run in runs {
+        let mut current: Vec<(u32, u32)> = Vec::new();
+
+        for p in run {
+            if let Some(&last) = current.last() {
+                if label(last) != label(p) {
+                    res.push(std::mem::take(&mut current));
+                }
+            }
+
+            current.push(p);
+        }
+
+        if !current.is_empty() {
+            res.push(current);
+        }
+    }
+
+    res
+}
+
+fn label_map(image: &RgbaImage, map: &RgbaImage) -> Vec<u32> {
+    let (width, height) = image.dimensions();
+    let resized;
+    let map = if map.dimensions() != (width, height) {
+        resized = imageops::resize(map, width, height, imageops::FilterType::Nearest);
+        &resized
+    } else {
+        map
+    };
+
+    map.pixels().map(|p| u32::from_be_bytes(p.0)).collect()
+}
+
+fn kmeans(image: &RgbaImage, k: u32, seed: u64) -> Vec<u32> {
+    let pixels = image.pixels().map(|p| color(&p.0)).collect::<Vec<_>>();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut centres = Vec::with_capacity(k as usize);
+    let mut nearest = vec![f32::MAX; pixels.len()];
+
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    // k-means++ seeding, each new centre is picked with a chance proportional to its distance
+    centres.push(pixels[rng.gen_range(0, pixels.len())]);
+
+    while centres.len() < k.max(1) as usize {
+        let last = *centres.last().unwrap();
+
+        for (n, p) in nearest.iter_mut().zip(&pixels) {
+            *n = n.min(distance(p, &last));
+        }
+
+        let total = nearest.iter().map(|&n| f64::from(n)).sum::<f64>();
+
+        if total == 0.0 {
+            break;
+        }
+
+        let mut pick = rng.gen_range(0.0, total);
+        let idx = nearest
+            .iter()
+            .position(|&n| {
+                pick -= f64::from(n);
+                pick <= 0.0
+            })
+            .unwrap_or(pixels.len() - 1);
+
+        centres.push(pixels[idx]);
+    }
+
+    let mut labels = vec![0; pixels.len()];
+
+    for _ in 0..ITERATIONS {
+        let mut changed = false;
+        let mut sums = vec![([0.0; 3], 0.0); centres.len()];
+
+        for (label, p) in labels.iter_mut().zip(&pixels) {
+            let best = (0..centres.len())
+                .min_by(|&a, &b| distance(p, &centres[a]).total_cmp(&distance(p, &centres[b])))
+                .unwrap() as u32;
+
+            changed |= *label != best;
+            *label = best;
+
+            let sum = &mut sums[best as usize];
+
+            for (s, c) in sum.0.iter_mut().zip(p) {
+                *s += c;
+            }
+
+            sum.1 += 1.0;
+        }
+
+        for (centre, (sum, n)) in centres.iter_mut().zip(sums) {
+            if n > 0.0 {
+                *centre = [sum[0] / n, sum[1] / n, sum[2] / n];
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    labels
+}
+
+fn slic(image: &RgbaImage, size: u32, compactness: f32) -> Vec<u32> {
+    let (width, height) = image.dimensions();
+    let size = size.max(1);
+    let pixels = image.pixels().map(|p| color(&p.0)).collect::<Vec<_>>();
+    let mut centres = Vec::new();
+
+    for y in (size / 2..height).step_by(size as usize) {
+        for x in (size / 2..width).step_by(size as usize) {
+            let p = pixels[(y * width + x) as usize];
+
+            centres.push((p, x as f32, y as f32));
+        }
+    }
+
+    if centres.is_empty() {
+        return vec![0; pixels.len()];
+    }
+
+    let weight = (compactness / size as f32).powi(2);
+    let mut labels = vec![0u32; pixels.len()];
+
+    for _ in 0..ITERATIONS {
+        let mut best = vec![f32::MAX; pixels.len()];
+
+        for (i, &(c, cx, cy)) in centres.iter().enumerate() {
+            let x0 = (cx - size as f32).max(0.0) as u32;
+            let y0 = (cy - size as f32).max(0.0) as u32;
+            let x1 = ((cx + size as f32) as u32).min(width - 1);
+            let y1 = ((cy + size as f32) as u32).min(height - 1);
+
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    let idx = (y * width + x) as usize;
+                    let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+                    let d = distance(&pixels[idx], &c) + (dx * dx + dy * dy) * weight;
+
+                    if d < best[idx] {
+                        best[idx] = d;
+                        labels[idx] = i as u32;
+                    }
+                }
+            }
+        }
+
+        let mut sums = vec![([0.0; 3], 0.0, 0.0, 0.0); centres.len()];
+
+        for (idx, &label) in labels.iter().enumerate() {
+            let sum = &mut sums[label as usize];
+            let p = pixels[idx];
+
+            for (s, c) in sum.0.iter_mut().zip(p) {
+                *s += c;
+            }
+
+            sum.1 += (idx as u32 % width) as f32;
+            sum.2 += (idx as u32 / width) as f32;
+            sum.3 += 1.0;
+        }
+
+        for (centre, (c, x, y, n)) in centres.iter_mut().zip(sums) {
+            if n > 0.0 {
+                *centre = ([c[0] / n, c[1] / n, c[2] / n], x / n, y / n);
+            }
+        }
+    }
+
+    connect(&labels, width, height, (size * size / 4) as usize)
+}
+
+// give every connected piece its own label, merging pieces smaller than `min` into a neighbour
+fn connect(labels: &[u32], width: u32, height: u32, min: usize) -> Vec<u32> {
+    let mut res = vec![u32::MAX; labels.len()];
+    let mut next = 0;
+    let mut queue = VecDeque::new();
+    let mut piece = Vec::new();
+
+    for start in 0..labels.len() {
+        if res[start] != u32::MAX {
+            continue;
+        }
+
+        let mut adjacent = None;
+
+        queue.push_back(start);
+        res[start] = next;
+        piece.clear();
+
+        while let Some(idx) = queue.pop_front() {
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+
+            piece.push(idx);
+
+            for (nx, ny) in [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ] {
+                if nx >= width || ny >= height {
+                    continue;
+                }
+
+                let n = (ny * width + nx) as usize;
+
+                if res[n] == u32::MAX && labels[n] == labels[start] {
+                    res[n] = next;
+                    queue.push_back(n);
+                } else if res[n] != u32::MAX && res[n] != next {
+                    adjacent = Some(res[n]);
+                }
+            }
+        }
+
+        match adjacent {
+            Some(label) if piece.len() < min => {
+                for &idx in &piece {
+                    res[idx] = label;
+                }
+            }
+            _ => next += 1,
+        }
+    }
+
+    res
+}
+
+fn color(p: &[u8]) -> [f32; 3] {
+    [f32::from(p[0]), f32::from(p[1]), f32::from(p[2])]
+}
+
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}